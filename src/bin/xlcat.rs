@@ -0,0 +1,1124 @@
+//! This is the command-line interface to the xl library. It parses the arguments passed on the
+//! command line, determines if we can act on those arguments, and then provides a `Config` object
+//! back that can be passed into the `run` function if we can.
+//!
+//! In order to call `xlcat`, you need to provide a path to a valid workbook and (optionally) a tab
+//! that can be found in that workbook (either by name or by number). If you leave out the tab, we
+//! print the sheet that was active when the workbook was last saved (or the only sheet, if the
+//! workbook has just one). You can (optionally) also pass the number of rows you want to see with
+//! the `-n` flag (e.g., `-n 10` limits the output to the first ten rows), or `--tail 10` to see
+//! the last ten. Pass `--formulas` (or `--show-formulas`) to see the formula behind each cell
+//! instead of its cached value, or `--display` to see each value the way Excel shows it (i.e.,
+//! formatted with its number format). Pass `--transpose` to print the columns of the tab as rows
+//! (and its rows as columns); since nothing can be printed until the last row has been read, the
+//! rows you select (with `-n` or `--tail`) are held in memory rather than streamed.
+//!
+//! Rows are printed as comma-separated values by default. With `--fmt jsonl`, each row is printed
+//! as a JSON object (one per line) whose keys come from the first row of the tab, or are the
+//! column letters if you also pass `--no-header`. Blank cells are empty in CSV and null in JSON
+//! unless you pass `--na TEXT` (e.g., `--na NA`) to print something else for them. Errors (e.g.,
+//! `#N/A`) are printed as they are, unless you pass `--blank-errors` to treat them as blank cells.
+//!
+//! To write the same rows in several formats at once, pass `--out PATH` once per file (e.g.,
+//! `--out data.csv --out data.jsonl`). The format of each file comes from its extension, and the
+//! worksheet is only read once however many files you ask for.
+//!
+//! xlcat exits with status 0 when it succeeds, 1 for bad arguments (or any failure not listed
+//! here), 2 when the tab cannot be found, 3 when the workbook file does not exist, and 4 when the
+//! workbook cannot be read. Pass `--quiet` to rely on the status alone: the messages for a missing
+//! tab or file (including the list of available tabs) are left out.
+//!
+//! Pass `--manifest` to get a JSON description of the workbook (its date system, its document
+//! properties, and the name, position, state, used area, and tab color of each sheet) instead of
+//! the rows of a tab.
+//!
+//! Output is UTF-8 without a byte order mark unless you ask otherwise: `--bom` starts CSV output
+//! with a byte order mark (which Excel on Windows needs to recognize UTF-8), and, when xlcat is
+//! built with the `encoding` feature, `--encoding LABEL` (e.g., `--encoding latin1`) writes the
+//! output in another encoding.
+
+use std::collections::VecDeque;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::process;
+use xl::{Cell, DateSystem, ExcelValue, ReadOptions, Row, Workbook};
+
+/// The exit status for bad arguments, and for any failure without a more specific status
+const EXIT_FAILURE: i32 = 1;
+/// The exit status when the tab asked for (or any tab, if none was given) cannot be found
+const EXIT_NO_SHEET: i32 = 2;
+/// The exit status when the workbook file does not exist
+const EXIT_NO_FILE: i32 = 3;
+/// The exit status when the workbook cannot be read (e.g., it is not an xlsx file)
+const EXIT_BAD_WORKBOOK: i32 = 4;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let config = Config::new(&args).unwrap_or_else(|err| {
+        match err {
+            ConfigError::NeedPathAndTab(_) => {
+                eprintln!("Error: {}", err);
+                usage();
+            },
+            _ => {
+                eprintln!("Error: {}", err);
+                eprintln!("\nSee help by using -h flag.");
+            },
+        }
+        process::exit(EXIT_FAILURE);
+    });
+    if let Err(e) = run(&config) {
+        // with --quiet, the exit status is all you get for the errors a script can expect
+        if !(config.quiet && e.is_expected()) {
+            eprintln!("Error: {}", e);
+        }
+        if let RunError::NeedTab = e {
+            if !config.quiet {
+                if let Ok(mut wb) = Workbook::open(&config.workbook_path) {
+                    list_sheets(&mut wb, &config.workbook_path);
+                }
+                eprintln!("\nSee help by using -h flag.");
+            }
+        }
+        process::exit(e.exit_code());
+    }
+}
+
+/// Why `run` failed
+#[derive(Debug, PartialEq)]
+enum RunError {
+    /// No tab was given and the workbook has several without recording which one is active
+    NeedTab,
+    SheetNotFound,
+    FileNotFound(String),
+    BadWorkbook(String),
+    /// Anything else (e.g., we could not write the output)
+    Other(String),
+}
+
+impl RunError {
+    fn exit_code(&self) -> i32 {
+        match self {
+            RunError::NeedTab | RunError::SheetNotFound => EXIT_NO_SHEET,
+            RunError::FileNotFound(_) => EXIT_NO_FILE,
+            RunError::BadWorkbook(_) => EXIT_BAD_WORKBOOK,
+            RunError::Other(_) => EXIT_FAILURE,
+        }
+    }
+
+    /// Is this an error a script can expect (and branch on), rather than something going wrong?
+    fn is_expected(&self) -> bool {
+        matches!(self, RunError::NeedTab | RunError::SheetNotFound | RunError::FileNotFound(_))
+    }
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunError::NeedTab => write!(f, "must also provide which tab you want to view in workbook (it has several and does not record an active one)"),
+            RunError::SheetNotFound => write!(f, "that sheet does not exist"),
+            RunError::FileNotFound(path) => write!(f, "'{}' does not exist", path),
+            RunError::BadWorkbook(msg) | RunError::Other(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+enum SheetNameOrNum {
+    Name(String),
+    Num(usize),
+}
+
+struct Config {
+    /// Which xlsx file should we print?
+    workbook_path: String,
+    /// Which tab should we print? (`None` means the sheet that was active when the workbook was
+    /// saved.)
+    tab: Option<SheetNameOrNum>,
+    /// How many rows should we print?
+    nrows: Option<u32>,
+    /// Should we only print the last few rows (after any `-n` limit)?
+    tail: Option<usize>,
+    /// Should we print formulas (e.g., "=A1+B1") instead of their values?
+    formulas: bool,
+    /// Should we print each value the way Excel shows it (i.e., with its number format)?
+    display: bool,
+    /// Should we print the columns of the selected rows as rows?
+    transpose: bool,
+    /// What should we print for blank cells? (`None` means nothing in CSV and null in JSON.)
+    na: Option<String>,
+    /// Should we print errors (e.g., #N/A) as if their cells were blank?
+    blank_errors: bool,
+    /// How should we print each row?
+    fmt: OutputFormat,
+    /// Files to write to instead of stdout (each in the format its extension calls for)
+    outputs: Vec<Output>,
+    /// Is the first row of the tab data (rather than column names)?
+    no_header: bool,
+    /// Should CSV output start with a byte order mark?
+    bom: bool,
+    /// Which encoding should we write the output in?
+    #[cfg(feature = "encoding")]
+    encoding: &'static encoding_rs::Encoding,
+    /// Should we print a JSON description of the workbook and its sheets instead of any rows?
+    manifest: bool,
+    /// Should we keep quiet about the errors a script can expect (see `RunError::is_expected`)?
+    quiet: bool,
+    /// Should we show usage information?
+    want_help: bool,
+    /// Should we show the current version?
+    want_version: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            workbook_path: "".to_owned(),
+            tab: None,
+            nrows: None,
+            tail: None,
+            formulas: false,
+            display: false,
+            transpose: false,
+            na: None,
+            blank_errors: false,
+            fmt: OutputFormat::Csv,
+            outputs: vec![],
+            no_header: false,
+            bom: false,
+            #[cfg(feature = "encoding")]
+            encoding: encoding_rs::UTF_8,
+            manifest: false,
+            quiet: false,
+            want_help: false,
+            want_version: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    /// Comma-separated values (the default)
+    Csv,
+    /// One JSON object per row, keyed by the header row (or by column letters)
+    JsonLines,
+}
+
+impl OutputFormat {
+    /// The format to write a file in, based on its extension (e.g., "data.csv" is CSV)
+    fn for_path(path: &str) -> Option<OutputFormat> {
+        let extension = path.rsplit_once('.')?.1.to_ascii_lowercase();
+        match &extension[..] {
+            "csv" => Some(OutputFormat::Csv),
+            "jsonl" | "json" | "ndjson" => Some(OutputFormat::JsonLines),
+            _ => None,
+        }
+    }
+}
+
+/// A file to write the rows to (see `--out`)
+#[derive(Debug, Clone, PartialEq)]
+struct Output {
+    path: String,
+    fmt: OutputFormat,
+}
+
+/// Every flag `Config::new` understands (so that it can tell them apart from the tab)
+const FLAGS: &[&str] = &[
+    "-n", "--tail", "--formulas", "--show-formulas", "--display", "--transpose", "--na",
+    "--blank-errors", "--fmt", "--out", "--no-header", "--bom", "-q", "--quiet", "--manifest",
+    "--encoding",
+];
+
+/// Why the command line could not be understood. Each variant holds (a copy of) what was typed,
+/// so the error can outlive the arguments it came from.
+#[derive(Debug, PartialEq)]
+enum ConfigError {
+    NeedPathAndTab(String),
+    RowsMustBeInt(String),
+    NeedNumRows(String),
+    UnknownFlag(String),
+    NeedNa,
+    NeedFormat,
+    UnknownFormat(String),
+    NeedOutPath,
+    UnknownExtension(String),
+    #[cfg(feature = "encoding")]
+    NeedEncoding,
+    #[cfg(feature = "encoding")]
+    UnknownEncoding(String),
+}
+
+impl std::error::Error for ConfigError {}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::NeedPathAndTab(exe) => write!(f, "need to provide path and tab when running '{}'. See usage below.", exe),
+            ConfigError::RowsMustBeInt(nrows) => write!(f, "number of rows must be an integer value, got '{}'", nrows),
+            ConfigError::NeedNumRows(flag) => write!(f, "must provide number of rows when using {}", flag),
+            ConfigError::UnknownFlag(flag) => write!(f, "unknown flag: '{}'", flag),
+            ConfigError::NeedNa => write!(f, "must provide the text to print for blank cells when using --na"),
+            ConfigError::NeedFormat => write!(f, "must provide an output format when using --fmt (csv or jsonl)"),
+            ConfigError::UnknownFormat(fmt) => write!(f, "unknown output format: '{}' (expected csv or jsonl)", fmt),
+            ConfigError::NeedOutPath => write!(f, "must provide a file to write to when using --out"),
+            ConfigError::UnknownExtension(path) => write!(f, "cannot tell which format to write '{}' in (use a .csv or .jsonl extension)", path),
+            #[cfg(feature = "encoding")]
+            ConfigError::NeedEncoding => write!(f, "must provide an encoding when using --encoding (e.g., utf-8 or latin1)"),
+            #[cfg(feature = "encoding")]
+            ConfigError::UnknownEncoding(label) => write!(f, "unknown encoding: '{}'", label),
+        }
+    }
+}
+
+impl Config {
+    fn new(args: &[String]) -> Result<Config, ConfigError> {
+        if args.len() < 2 {
+            return Err(ConfigError::NeedPathAndTab(args[0].clone()))
+        }
+        match args[1].as_ref() {
+            "-h" | "--help" => return Ok(Config { want_help: true, ..Config::default() }),
+            "-v" | "--version" => return Ok(Config { want_version: true, ..Config::default() }),
+            _ => (),
+        }
+        let workbook_path = args[1].clone();
+        // the tab is optional, so a flag we know is taken as one; anything else is the tab, even
+        // if it starts with a dash (e.g., a tab named "-2024")
+        let (tab, flags) = match args.get(2) {
+            Some(tab) if !FLAGS.contains(&&tab[..]) => {
+                let tab = match tab.parse::<usize>() {
+                    Ok(num) => SheetNameOrNum::Num(num),
+                    Err(_) => SheetNameOrNum::Name(tab.clone())
+                };
+                (Some(tab), &args[3..])
+            },
+            _ => (None, &args[2..]),
+        };
+        let mut config = Config { workbook_path, tab, ..Config::default() };
+        let mut iter = flags.iter();
+        while let Some(flag) = iter.next() {
+            let flag = &flag[..];
+            match flag {
+                "-n" => {
+                    if let Some(nrows) = iter.next() {
+                        if let Ok(nrows) = nrows.parse::<u32>() {
+                            config.nrows = Some(nrows)
+                        } else {
+                            return Err(ConfigError::RowsMustBeInt(nrows.clone()))
+                        }
+                    } else {
+                        return Err(ConfigError::NeedNumRows(flag.to_owned()))
+                    }
+                },
+                "--tail" => {
+                    let tail = iter.next().ok_or_else(|| ConfigError::NeedNumRows(flag.to_owned()))?;
+                    config.tail = Some(tail.parse::<usize>().map_err(|_| ConfigError::RowsMustBeInt(tail.clone()))?);
+                },
+                "--formulas" | "--show-formulas" => config.formulas = true,
+                "--display" => config.display = true,
+                "--transpose" => config.transpose = true,
+                "--na" => config.na = Some(iter.next().ok_or(ConfigError::NeedNa)?.clone()),
+                "--blank-errors" => config.blank_errors = true,
+                "--fmt" => {
+                    config.fmt = match iter.next().map(|fmt| &fmt[..]) {
+                        Some("csv") => OutputFormat::Csv,
+                        Some("jsonl") => OutputFormat::JsonLines,
+                        Some(fmt) => return Err(ConfigError::UnknownFormat(fmt.to_owned())),
+                        None => return Err(ConfigError::NeedFormat),
+                    }
+                },
+                "--out" => {
+                    let path = iter.next().ok_or(ConfigError::NeedOutPath)?;
+                    let fmt = OutputFormat::for_path(path).ok_or_else(|| ConfigError::UnknownExtension(path.clone()))?;
+                    config.outputs.push(Output { path: path.clone(), fmt });
+                },
+                "--no-header" => config.no_header = true,
+                "--bom" => config.bom = true,
+                "-q" | "--quiet" => config.quiet = true,
+                "--manifest" => config.manifest = true,
+                #[cfg(feature = "encoding")]
+                "--encoding" => {
+                    let label = iter.next().ok_or(ConfigError::NeedEncoding)?;
+                    config.encoding = encoding_rs::Encoding::for_label(label.as_bytes())
+                        .ok_or_else(|| ConfigError::UnknownEncoding(label.clone()))?;
+                },
+                _ => return Err(ConfigError::UnknownFlag(flag.to_owned())),
+            }
+        }
+        Ok(config)
+    }
+
+    /// The `ReadOptions` that match the flags given on the command line
+    fn read_options(&self) -> ReadOptions {
+        let mut opts = ReadOptions::new();
+        if let Some(nrows) = self.nrows {
+            // the header row does not count towards the rows we print (unless we transpose them,
+            // in which case the header is the first column)
+            let header = if self.uses_header() && !self.transpose { 1 } else { 0 };
+            opts = opts.max_rows(nrows as usize + header);
+        }
+        opts
+    }
+
+    /// Wrap `out` (stdout or a file) in an encoder if the user asked for an encoding other than
+    /// UTF-8
+    fn writer<'a, W: Write + 'a>(&self, out: W) -> Box<dyn Write + 'a> {
+        #[cfg(feature = "encoding")]
+        {
+            if self.encoding != encoding_rs::UTF_8 {
+                return Box::new(EncodingWriter::new(out, self.encoding))
+            }
+        }
+        Box::new(out)
+    }
+
+    /// The byte order mark to start output in `fmt` with (empty unless the user asked for one
+    /// with CSV output, or if the output encoding does not have one)
+    fn bom(&self, fmt: OutputFormat) -> &'static [u8] {
+        if !self.bom || fmt != OutputFormat::Csv {
+            return b""
+        }
+        #[cfg(feature = "encoding")]
+        {
+            if self.encoding != encoding_rs::UTF_8 {
+                return b""
+            }
+        }
+        b"\xEF\xBB\xBF"
+    }
+
+    /// The formats we write in (one per `--out` file, or the `--fmt` for stdout)
+    fn formats(&self) -> Vec<OutputFormat> {
+        if self.outputs.is_empty() {
+            vec![self.fmt]
+        } else {
+            self.outputs.iter().map(|output| output.fmt).collect()
+        }
+    }
+
+    /// Do we read the first row of the tab as column names?
+    fn uses_header(&self) -> bool {
+        self.formats().contains(&OutputFormat::JsonLines) && !self.no_header
+    }
+}
+
+fn run(config: &Config) -> Result<(), RunError> {
+    if config.want_help {
+        usage();
+        std::process::exit(0);
+    }
+    if config.want_version {
+        version();
+        std::process::exit(0);
+    }
+    if !std::path::Path::new(&config.workbook_path).exists() {
+        return Err(RunError::FileNotFound(config.workbook_path.clone()))
+    }
+    let mut wb = Workbook::new(&config.workbook_path).map_err(RunError::BadWorkbook)?;
+    if config.manifest {
+        let stdout = io::stdout();
+        let mut out = config.writer(stdout.lock());
+        return writeln!(out, "{}", manifest(&mut wb)).and_then(|_| out.flush())
+            .map_err(|e| RunError::Other(e.to_string()))
+    }
+    let sheets = wb.sheets();
+    let sheet = match &config.tab {
+        Some(SheetNameOrNum::Name(n)) => sheets.get(&n[..]).ok_or(RunError::SheetNotFound)?,
+        Some(SheetNameOrNum::Num(n)) => sheets.get(*n).ok_or(RunError::SheetNotFound)?,
+        // without a tab, we use the active sheet or, failing that, the only sheet there is
+        None => sheets.active()
+            .or_else(|| if sheets.len() == 1 { sheets.get(1) } else { None })
+            .ok_or(RunError::NeedTab)?,
+    };
+    let opts = config.read_options();
+    let rows = sheet.rows_with(&mut wb, &opts).map_err(|e| RunError::BadWorkbook(e.to_string()))?;
+    let stdout = io::stdout();
+    let mut sinks = Vec::with_capacity(config.outputs.len());
+    for output in &config.outputs {
+        let file = fs::File::create(&output.path)
+            .map_err(|e| RunError::Other(format!("could not create {}: {}", output.path, e)))?;
+        sinks.push(Sink::new(config.writer(io::BufWriter::new(file)), output.fmt));
+    }
+    if sinks.is_empty() {
+        sinks.push(Sink::new(config.writer(stdout.lock()), config.fmt));
+    }
+    write_rows(rows, &mut sinks, config).map_err(RunError::Other)
+}
+
+/// Somewhere we write rows to (stdout or one of the `--out` files) in one of the output formats
+struct Sink<'a> {
+    out: Box<dyn Write + 'a>,
+    fmt: OutputFormat,
+    /// The JSON keys of each column (once we have seen the first row)
+    keys: Option<Vec<String>>,
+    /// How many rows we have written (not counting a header)
+    rows_written: usize,
+}
+
+impl<'a> Sink<'a> {
+    fn new(out: Box<dyn Write + 'a>, fmt: OutputFormat) -> Self {
+        Sink { out, fmt, keys: None, rows_written: 0 }
+    }
+
+    fn write_row(&mut self, row: &Row, config: &Config) -> Result<(), String> {
+        if self.fmt == OutputFormat::JsonLines && self.keys.is_none() {
+            if !config.no_header {
+                self.keys = Some(header_keys(row));
+                return Ok(())
+            }
+            self.keys = Some(column_keys(row));
+        }
+        // when another output needs a header row, we read one more row than this one prints (once
+        // transposed, `-n` has already picked the columns, and any number of rows can come out)
+        if !config.transpose && config.nrows.is_some_and(|n| self.rows_written >= n as usize) {
+            return Ok(())
+        }
+        self.rows_written += 1;
+        match self.fmt {
+            OutputFormat::Csv => {
+                writeln!(self.out, "{}", csv_row(row, config)).map_err(|e| e.to_string())
+            },
+            OutputFormat::JsonLines => {
+                let keys = self.keys.as_ref().expect("keys are set above");
+                print_json_line(&mut *self.out, row, keys, config)
+            },
+        }
+    }
+}
+
+/// Write every row to each of the `sinks`, so the worksheet is only read once however many
+/// outputs there are
+fn write_rows<'r>(rows: impl Iterator<Item = Row<'r>>, sinks: &mut [Sink], config: &Config) -> Result<(), String> {
+    for sink in sinks.iter_mut() {
+        sink.out.write_all(config.bom(sink.fmt)).map_err(|e| e.to_string())?;
+    }
+    match config.tail {
+        // the rows `-n` and `--tail` select are turned into columns, so they are all needed first
+        _ if config.transpose => {
+            let selected: Vec<Row> = match config.tail {
+                None => rows.collect(),
+                Some(n) => {
+                    let (first, last) = tail(rows, n);
+                    first.filter(|_| last.len() < n).into_iter().chain(last).collect()
+                },
+            };
+            for row in transpose(selected) {
+                for sink in sinks.iter_mut() {
+                    sink.write_row(&row, config)?;
+                }
+            }
+        },
+        None => {
+            for row in rows {
+                for sink in sinks.iter_mut() {
+                    sink.write_row(&row, config)?;
+                }
+            }
+        },
+        Some(n) => {
+            let (first, last) = tail(rows, n);
+            for sink in sinks.iter_mut() {
+                if let Some(first) = &first {
+                    // a JSON header row is always needed for the keys; otherwise the first row is
+                    // only printed if it is one of the last `n`
+                    let header = sink.fmt == OutputFormat::JsonLines && !config.no_header;
+                    if header || last.len() < n {
+                        sink.write_row(first, config)?;
+                    }
+                }
+                for row in &last {
+                    sink.write_row(row, config)?;
+                }
+            }
+        },
+    }
+    for sink in sinks.iter_mut() {
+        sink.out.flush().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Turn the columns of `rows` into rows (and its rows into columns). Rows shorter than the widest
+/// one are padded with blank cells first. Each cell's reference is changed to where it ends up, so
+/// the column letters (e.g., the JSON keys with `--no-header`) match the transposed layout.
+fn transpose(rows: Vec<Row>) -> Vec<Row> {
+    let width = rows.iter().map(|row| row.0.len()).max().unwrap_or(0);
+    let mut columns: Vec<Vec<Cell>> = (0..width).map(|_| Vec::with_capacity(rows.len())).collect();
+    for row in rows {
+        let padding = width - row.0.len();
+        let cells = row.0.into_iter().chain(std::iter::repeat_with(Cell::default).take(padding));
+        for (column, cell) in columns.iter_mut().zip(cells) {
+            column.push(cell);
+        }
+    }
+    columns.into_iter().enumerate().map(|(i, mut cells)| {
+        for (j, cell) in cells.iter_mut().enumerate() {
+            cell.reference = format!("{}{}", xl::num2col(j as u16 + 1).unwrap_or_default(), i + 1);
+        }
+        Row(cells, i + 1)
+    }).collect()
+}
+
+/// Split `rows` into the first row and (at most) the last `n` rows after it. We cannot seek back
+/// through a worksheet, so the last rows are kept in a ring buffer as we go: only `n` rows are ever
+/// held in memory, however long the worksheet is.
+fn tail<'r>(mut rows: impl Iterator<Item = Row<'r>>, n: usize) -> (Option<Row<'r>>, VecDeque<Row<'r>>) {
+    let first = rows.next();
+    let mut last = VecDeque::with_capacity(n);
+    if n == 0 {
+        return (first, last)
+    }
+    for row in rows {
+        if last.len() == n {
+            last.pop_front();
+        }
+        last.push_back(row);
+    }
+    (first, last)
+}
+
+/// Format `row` as a line of comma-separated values
+fn csv_row(row: &Row, config: &Config) -> String {
+    let na = config.na.as_deref().unwrap_or("");
+    if !config.formulas && !config.display && !config.blank_errors {
+        return row.to_csv(na)
+    }
+    let cells: Vec<String> = row.0.iter().map(|cell| {
+        if config.formulas && !cell.formula.is_empty() {
+            format!("\"={}\"", cell.formula.replace('"', "\"\""))
+        } else if cell.is_blank() || config.blank_errors && cell.is_error() {
+            csv_field(na)
+        } else if config.display {
+            csv_field(&cell.formatted_value())
+        } else {
+            cell.to_string()
+        }
+    }).collect();
+    cells.join(",")
+}
+
+/// Quote `text` for CSV if it needs it (i.e., if it has a comma, quote, or line break in it)
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_owned()
+    }
+}
+
+/// The JSON keys to use for each column, taken from the text of the header row (columns without
+/// a header use their column letter instead)
+fn header_keys(header: &Row) -> Vec<String> {
+    let letters = column_keys(header);
+    header.0.iter().zip(letters).map(|(cell, letter)| match &cell.value {
+        ExcelValue::None => letter,
+        ExcelValue::String(s) => s.to_string(),
+        ExcelValue::Time(t) => t.to_string(),
+        value => value.to_string(),
+    }).collect()
+}
+
+/// The column letters of each cell in `row` (e.g., "A", "B", ...)
+fn column_keys(row: &Row) -> Vec<String> {
+    row.0.iter().enumerate().map(|(i, cell)| {
+        match xl::split_reference(&cell.reference) {
+            Some((letters, _)) => letters.to_owned(),
+            None => xl::num2col(i as u16 + 1).unwrap_or_default(),
+        }
+    }).collect()
+}
+
+/// Print `row` as a JSON object on its own line. Stdout is line-buffered, so a program reading
+/// from xlcat gets each row as soon as it is read.
+fn print_json_line(out: &mut dyn Write, row: &Row, keys: &[String], config: &Config) -> Result<(), String> {
+    let fields: Vec<String> = row.0.iter().zip(keys).map(|(cell, key)| {
+        format!("{}:{}", json_string(key), json_cell(cell, config))
+    }).collect();
+    writeln!(out, "{{{}}}", fields.join(",")).map_err(|e| e.to_string())
+}
+
+/// The JSON value of a cell. Numbers and booleans become JSON numbers and booleans, blanks become
+/// null (or the `--na` text), and everything else (text, dates, times, and errors) becomes a
+/// string. With `--display`, every value that is not blank is the string Excel would show. With
+/// `--blank-errors`, errors are treated like blanks.
+fn json_cell(cell: &Cell, config: &Config) -> String {
+    if config.formulas && !cell.formula.is_empty() {
+        return json_string(&format!("={}", cell.formula))
+    }
+    match &cell.value {
+        ExcelValue::None => config.na.as_deref().map_or_else(|| "null".to_owned(), json_string),
+        ExcelValue::Error(_) if config.blank_errors => config.na.as_deref().map_or_else(|| "null".to_owned(), json_string),
+        _ if config.display => json_string(&cell.formatted_value()),
+        ExcelValue::Bool(b) => b.to_string(),
+        ExcelValue::Number(n) if n.is_finite() => n.to_string(),
+        ExcelValue::Number(_) => "null".to_owned(),
+        ExcelValue::String(s) => json_string(s),
+        ExcelValue::Date(d) => json_string(&d.to_string()),
+        ExcelValue::DateTime(d) => json_string(&d.format("%Y-%m-%dT%H:%M:%S").to_string()),
+        ExcelValue::Time(t) => json_string(&t.to_string()),
+        ExcelValue::Error(e) => json_string(&e.to_string()),
+    }
+}
+
+/// Describe the workbook as a JSON object: its date system, its document properties, and the
+/// name, position, state (visible, hidden, or veryHidden), used area, and tab color of each sheet.
+/// The keys always come in the same order and missing values are null, so the output is easy to
+/// compare from one file to the next. Only the workbook xml and the start of each worksheet are
+/// read, so this is quick however much data the sheets hold.
+fn manifest(wb: &mut Workbook) -> String {
+    let opt = |value: Option<&str>| value.map_or_else(|| "null".to_owned(), json_string);
+    let date_system = match wb.date_system {
+        DateSystem::V1900 => "1900",
+        DateSystem::V1904 => "1904",
+    };
+    let props = wb.properties();
+    let properties = [
+        ("creator", &props.creator),
+        ("created", &props.created),
+        ("modified", &props.modified),
+        ("last_modified_by", &props.last_modified_by),
+        ("title", &props.title),
+        ("application", &props.application),
+        ("company", &props.company),
+    ].iter().map(|(key, value)| format!("{}:{}", json_string(key), opt(value.as_deref()))).collect::<Vec<_>>();
+    let sheets = wb.sheets();
+    let sheets: Vec<String> = sheets.worksheets().map(|ws| {
+        format!(
+            "{{\"name\":{},\"position\":{},\"state\":{},\"dimension\":{},\"tab_color\":{}}}",
+            json_string(&ws.name),
+            ws.position,
+            json_string(ws.state.as_str()),
+            opt(ws.dimension(wb).as_deref()),
+            opt(ws.tab_color(wb).as_deref()),
+        )
+    }).collect();
+    format!(
+        "{{\"date_system\":{},\"properties\":{{{}}},\"sheets\":[{}]}}",
+        json_string(date_system),
+        properties.join(","),
+        sheets.join(","),
+    )
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// A writer that takes UTF-8 text and writes it to `inner` in another encoding. Characters the
+/// encoding cannot represent are written as HTML numeric character references (e.g., "&#8364;"),
+/// which is what `encoding_rs` does.
+#[cfg(feature = "encoding")]
+struct EncodingWriter<W: Write> {
+    inner: W,
+    encoding: &'static encoding_rs::Encoding,
+    /// The start of a character that was split across two writes
+    pending: Vec<u8>,
+}
+
+#[cfg(feature = "encoding")]
+impl<W: Write> EncodingWriter<W> {
+    fn new(inner: W, encoding: &'static encoding_rs::Encoding) -> Self {
+        EncodingWriter { inner, encoding, pending: Vec::new() }
+    }
+}
+
+#[cfg(feature = "encoding")]
+impl<W: Write> Write for EncodingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let complete = match std::str::from_utf8(&self.pending) {
+            Ok(text) => text.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        };
+        let text = std::str::from_utf8(&self.pending[..complete]).expect("checked above");
+        let (bytes, _, _) = self.encoding.encode(text);
+        self.inner.write_all(&bytes)?;
+        self.pending.drain(..complete);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Tell the user which sheets they can choose from
+fn list_sheets(wb: &mut Workbook, path: &str) {
+    eprintln!("The following sheets are available in '{}':", path);
+    for sheet_name in wb.sheet_names() {
+        eprintln!("   {}", sheet_name);
+    }
+}
+
+fn usage() {
+    println!(concat!(
+        "\n",
+        "xlcat ", env!("CARGO_PKG_VERSION"), "\n",
+        env!("CARGO_PKG_AUTHORS"), "\n",
+        "\n",
+        "xlcat is like cat, but for Excel files (xlsx files to be precise). You simply\n",
+        "give it the path of the xlsx and the tab you want to view, and it prints the\n",
+        "data in that tab to your screen in a comma-delimited format.\n",
+        "\n",
+        "You can read about the project at https://xlpro.tips/posts/xlcat. The project\n",
+        "page is hosted at https://github.com/xlprotips/xl.\n",
+        "\n",
+        "USAGE:\n",
+        "  xlcat PATH [TAB] [-n NUM] [--tail NUM] [--formulas] [--display] [--na TEXT]\n",
+        "        [--blank-errors] [--transpose] [--fmt FMT] [--out PATH]... [--no-header] [--bom] [--encoding LABEL]\n",
+        "        [--manifest] [-q | --quiet] [-h | --help]\n",
+        "\n",
+        "ARGS:\n",
+        "  PATH      Where the xlsx file is located on your filesystem.\n",
+        "  TAB       Which tab in the xlsx you want to print to screen (by name or\n",
+        "            number). Defaults to the tab that was active when the file was\n",
+        "            last saved (or the only tab, if there is just one).\n",
+        "\n",
+        "OPTIONS:\n",
+        "  -n <NUM>    Limit the number of rows we print to <NUM>.\n",
+        "  --tail <NUM> Only print the last <NUM> rows (of those -n leaves, if given).\n",
+        "  --formulas  Print the formula (e.g., =A1+B1) of cells that have one instead\n",
+        "              of the value Excel last calculated for them. --show-formulas\n",
+        "              does the same.\n",
+        "  --display   Print each value the way Excel shows it, using its number format\n",
+        "              (e.g., 1,234.50 or 7-Mar-21).\n",
+        "  --blank-errors\n",
+        "              Print errors (e.g., #N/A or #DIV/0!) as if their cells were\n",
+        "              blank (i.e., empty, null, or the --na text).\n",
+        "  --transpose Print the columns of the tab as rows. The selected rows are all\n",
+        "              read into memory before anything is printed.\n",
+        "  --na <TEXT> Print TEXT for blank cells (e.g., NA or NaN) instead of leaving\n",
+        "              the field empty (or null, with --fmt jsonl).\n",
+        "  --fmt <FMT> Print rows as csv (the default) or jsonl (one JSON object per\n",
+        "              row, keyed by the first row of the tab).\n",
+        "  --no-header With --fmt jsonl, treat the first row as data and key each\n",
+        "              value by its column letter instead.\n",
+        "  --out <PATH> Write the rows to PATH instead of the screen, as csv or jsonl\n",
+        "              depending on its extension. Repeat it to write several files\n",
+        "              while reading the tab only once.\n",
+        "  --bom       Start csv output with a UTF-8 byte order mark (which Excel on\n",
+        "              Windows needs to read it as UTF-8).\n",
+        "  --encoding <LABEL>\n",
+        "              Write the output in another encoding (e.g., latin1). Only\n",
+        "              available when xlcat is built with the encoding feature.\n",
+        "  --manifest  Print the workbook's date system, properties, and sheets (name,\n",
+        "              position, state, used area, and tab color) as JSON instead of\n",
+        "              the rows of a tab. No cell data is read, so this is quick.\n",
+        "  -q, --quiet Do not print anything when the workbook or tab cannot be found\n",
+        "              (check the exit status instead).\n",
+        "\n",
+        "EXIT STATUS:\n",
+        "  0  Success\n",
+        "  1  Bad arguments, or any other failure\n",
+        "  2  The tab was not found (or no tab was given and none could be chosen)\n",
+        "  3  The workbook file does not exist\n",
+        "  4  The workbook could not be read (e.g., it is not an xlsx file)\n",
+    ));
+}
+
+fn version() {
+    println!("{}", version_string());
+}
+
+/// The version banner (taken from Cargo.toml when xlcat is built, so it is never out of date)
+fn version_string() -> String {
+    format!("xlcat {}", env!("CARGO_PKG_VERSION"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn json_values() {
+        assert_eq!(json_string("a \"b\"\n\\"), r#""a \"b\"\n\\""#);
+        let config = Config::new(&args(&["xlcat", "book.xlsx", "1", "--fmt", "jsonl", "-n", "2"])).ok().unwrap();
+        assert_eq!(config.fmt, OutputFormat::JsonLines);
+        assert_eq!(config.read_options(), ReadOptions::new().max_rows(3));
+        assert!(Config::new(&args(&["xlcat", "book.xlsx", "1", "--fmt", "xml"])).is_err());
+    }
+
+    #[test]
+    fn errors_quote_what_was_typed() {
+        let message = |a: &[&str]| Config::new(&args(a)).err().unwrap().to_string();
+        assert_eq!(message(&["xlcat", "book.xlsx", "1", "-n", "ten"]), "number of rows must be an integer value, got 'ten'");
+        assert_eq!(message(&["xlcat", "book.xlsx", "1", "--nope"]), "unknown flag: '--nope'");
+        assert_eq!(message(&["xlcat", "book.xlsx", "1", "--fmt", "xml"]), "unknown output format: 'xml' (expected csv or jsonl)");
+    }
+
+    #[test]
+    fn config_errors_outlive_args() {
+        let err: Box<dyn std::error::Error> = {
+            let args = args(&["xlcat", "book.xlsx", "1", "--tail", "many"]);
+            Box::new(Config::new(&args).err().unwrap())
+        };
+        assert_eq!(err.to_string(), "number of rows must be an integer value, got 'many'");
+        assert_eq!(Config::new(&args(&["xlcat"])).err(), Some(ConfigError::NeedPathAndTab("xlcat".to_owned())));
+    }
+
+    #[test]
+    fn tab_is_optional() {
+        let config = Config::new(&args(&["xlcat", "book.xlsx"])).ok().unwrap();
+        assert!(config.tab.is_none());
+        let config = Config::new(&args(&["xlcat", "book.xlsx", "-n", "2"])).ok().unwrap();
+        assert!(config.tab.is_none());
+        assert_eq!(config.nrows, Some(2));
+        let config = Config::new(&args(&["xlcat", "book.xlsx", "Time"])).ok().unwrap();
+        assert!(matches!(config.tab, Some(SheetNameOrNum::Name(ref n)) if n == "Time"));
+        let config = Config::new(&args(&["xlcat", "book.xlsx", "-2024", "--quiet"])).ok().unwrap();
+        assert!(matches!(config.tab, Some(SheetNameOrNum::Name(ref n)) if n == "-2024"));
+        assert!(config.quiet);
+    }
+
+    #[test]
+    fn bom() {
+        let config = Config::new(&args(&["xlcat", "book.xlsx", "1", "--bom"])).ok().unwrap();
+        assert_eq!(config.bom(OutputFormat::Csv), b"\xEF\xBB\xBF");
+        assert_eq!(config.bom(OutputFormat::JsonLines), b"");
+        let config = Config::new(&args(&["xlcat", "book.xlsx", "1"])).ok().unwrap();
+        assert_eq!(config.bom(OutputFormat::Csv), b"");
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn encoded_output() {
+        let config = Config::new(&args(&["xlcat", "book.xlsx", "1", "--encoding", "latin1", "--bom"])).ok().unwrap();
+        assert_eq!(config.encoding, encoding_rs::WINDOWS_1252);
+        assert_eq!(config.bom(OutputFormat::Csv), b"");
+        assert!(Config::new(&args(&["xlcat", "book.xlsx", "1", "--encoding", "klingon"])).is_err());
+        let mut out = EncodingWriter::new(Vec::new(), encoding_rs::WINDOWS_1252);
+        let text = "caf\u{e9} \u{2603}".as_bytes();
+        // split the \u{e9} across two writes
+        out.write_all(&text[..4]).unwrap();
+        out.write_all(&text[4..]).unwrap();
+        assert_eq!(out.inner, b"caf\xE9 &#9731;");
+    }
+
+    #[test]
+    fn several_outputs() {
+        let config = Config::new(&args(&["xlcat", "book.xlsx", "1", "--out", "a.CSV", "--out", "b.jsonl", "-n", "2"])).ok().unwrap();
+        assert_eq!(config.formats(), vec![OutputFormat::Csv, OutputFormat::JsonLines]);
+        assert_eq!(config.read_options(), ReadOptions::new().max_rows(3));
+        assert!(Config::new(&args(&["xlcat", "book.xlsx", "1", "--out", "a.txt"])).is_err());
+        assert!(Config::new(&args(&["xlcat", "book.xlsx", "1", "--out"])).is_err());
+
+        let mut wb = Workbook::open("tests/data/frame.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let rows = sheets.get(1).unwrap().rows_with(&mut wb, &config.read_options()).unwrap();
+        let (mut csv, mut jsonl) = (Vec::new(), Vec::new());
+        let mut sinks = vec![
+            Sink::new(Box::new(&mut csv), OutputFormat::Csv),
+            Sink::new(Box::new(&mut jsonl), OutputFormat::JsonLines),
+        ];
+        write_rows(rows, &mut sinks, &config).unwrap();
+        drop(sinks);
+        let csv = String::from_utf8(csv).unwrap();
+        let jsonl = String::from_utf8(jsonl).unwrap();
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv.starts_with("\"Name\""));
+        assert_eq!(jsonl.lines().count(), 2);
+        assert!(jsonl.starts_with("{\"Name\":"));
+    }
+
+    #[test]
+    fn tail_rows() {
+        assert!(Config::new(&args(&["xlcat", "book.xlsx", "--tail", "x"])).is_err());
+        assert!(Config::new(&args(&["xlcat", "book.xlsx", "--tail"])).is_err());
+        let print = |a: &[&str]| {
+            let config = Config::new(&args(a)).ok().unwrap();
+            let mut wb = Workbook::open("tests/data/frame.xlsx").unwrap();
+            let sheets = wb.sheets();
+            let rows = sheets.get(1).unwrap().rows_with(&mut wb, &config.read_options()).unwrap();
+            let mut out = Vec::new();
+            write_rows(rows, &mut [Sink::new(Box::new(&mut out), config.fmt)], &config).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let all = print(&["xlcat", "book.xlsx"]);
+        let all: Vec<&str> = all.lines().collect();
+        let csv = print(&["xlcat", "book.xlsx", "--tail", "2"]);
+        assert_eq!(csv.lines().collect::<Vec<_>>(), all[all.len() - 2..]);
+        // asking for more rows than there are prints them all (including the first)
+        let csv = print(&["xlcat", "book.xlsx", "--tail", "100"]);
+        assert_eq!(csv.lines().collect::<Vec<_>>(), all);
+        assert_eq!(print(&["xlcat", "book.xlsx", "--tail", "0"]), "");
+        let csv = print(&["xlcat", "book.xlsx", "-n", "2", "--tail", "1"]);
+        assert_eq!(csv.lines().collect::<Vec<_>>(), all[1..2]);
+        // the header still names the JSON keys
+        let jsonl = print(&["xlcat", "book.xlsx", "--fmt", "jsonl", "--tail", "1"]);
+        assert_eq!(jsonl.lines().count(), 1);
+        assert!(jsonl.starts_with("{\"Name\":"));
+    }
+
+    #[test]
+    fn transposed() {
+        let print = |a: &[&str]| {
+            let config = Config::new(&args(a)).ok().unwrap();
+            let mut wb = Workbook::open("tests/data/frame.xlsx").unwrap();
+            let sheets = wb.sheets();
+            let rows = sheets.get(1).unwrap().rows_with(&mut wb, &config.read_options()).unwrap();
+            let mut out = Vec::new();
+            write_rows(rows, &mut [Sink::new(Box::new(&mut out), config.fmt)], &config).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let all = print(&["xlcat", "book.xlsx"]);
+        let all: Vec<Vec<&str>> = all.lines().map(|line| line.split(',').collect()).collect();
+        let csv = print(&["xlcat", "book.xlsx", "--transpose"]);
+        let transposed: Vec<Vec<&str>> = csv.lines().map(|line| line.split(',').collect()).collect();
+        assert_eq!(transposed.len(), all.iter().map(|row| row.len()).max().unwrap());
+        for (i, row) in all.iter().enumerate() {
+            for (j, field) in row.iter().enumerate() {
+                assert_eq!(transposed[j][i], *field);
+            }
+        }
+        // -n picks the rows that become columns
+        let csv = print(&["xlcat", "book.xlsx", "-n", "2", "--transpose"]);
+        assert_eq!(csv.lines().count(), transposed.len());
+        assert!(csv.lines().all(|line| line.split(',').count() == 2));
+        // the first column names the JSON keys
+        let jsonl = print(&["xlcat", "book.xlsx", "--fmt", "jsonl", "-n", "1", "--transpose"]);
+        assert_eq!(jsonl.lines().count(), transposed.len() - 1);
+        assert!(jsonl.starts_with("{\"Name\":"));
+
+        let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ragged = vec![
+            Row(sheets.get(1).unwrap().rows(&mut wb).unwrap().next().unwrap().0.into_iter().take(2).collect(), 1),
+            Row(vec![Cell::default()], 2),
+        ];
+        let rows = transpose(ragged);
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().all(|row| row.0.len() == 2));
+        assert!(rows[1][1].is_blank());
+        assert_eq!(rows[1][0].reference, "A2");
+    }
+
+    #[test]
+    fn display_values() {
+        let config = Config::new(&args(&["xlcat", "book.xlsx", "--display"])).ok().unwrap();
+        let mut wb = Workbook::open("tests/data/formatted.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let row = sheets.get(1).unwrap().rows(&mut wb).unwrap().next().unwrap();
+        assert_eq!(csv_row(&row, &config), "\"$1,234.50\",7-Mar-21,12.50%,(42),3.5");
+        let keys = column_keys(&row);
+        let mut out = Vec::new();
+        print_json_line(&mut out, &row, &keys, &config).unwrap();
+        assert!(String::from_utf8(out).unwrap().starts_with("{\"A\":\"$1,234.50\",\"B\":\"7-Mar-21\","));
+    }
+
+    #[test]
+    fn formula_cells() {
+        let mut wb = Workbook::open("tests/data/formulas.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let row = sheets.get(1).unwrap().rows(&mut wb).unwrap().next().unwrap();
+        let config = Config::new(&args(&["xlcat", "book.xlsx"])).ok().unwrap();
+        assert_eq!(csv_row(&row, &config), "1,2,3,\"x1\"");
+        assert_eq!(json_cell(&row.0[2], &config), "3");
+        for flag in &["--formulas", "--show-formulas"] {
+            let config = Config::new(&args(&["xlcat", "book.xlsx", flag])).ok().unwrap();
+            assert_eq!(csv_row(&row, &config), "1,2,\"=A1+B1\",\"=\"\"x\"\"&A1\"");
+            assert_eq!(json_cell(&row.0[0], &config), "1");
+            assert_eq!(json_cell(&row.0[2], &config), "\"=A1+B1\"");
+            assert_eq!(json_cell(&row.0[3], &config), "\"=\\\"x\\\"&A1\"");
+        }
+    }
+
+    #[test]
+    fn blank_cells() {
+        assert!(Config::new(&args(&["xlcat", "book.xlsx", "--na"])).is_err());
+        let mut wb = Workbook::open("tests/data/frame.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let rows: Vec<_> = sheets.get(1).unwrap().rows(&mut wb).unwrap().collect();
+        let (row, blank) = rows.iter()
+            .find_map(|row| Some((row, row.0.iter().position(|cell| cell.is_blank())?)))
+            .unwrap();
+        let config = Config::new(&args(&["xlcat", "book.xlsx"])).ok().unwrap();
+        assert_eq!(csv_row(row, &config).split(',').nth(blank), Some(""));
+        assert_eq!(json_cell(&row.0[blank], &config), "null");
+        for flags in &[&["--na", "NA"][..], &["--na", "NA", "--display"][..]] {
+            let config = Config::new(&args(&[&["xlcat", "book.xlsx"][..], flags].concat())).ok().unwrap();
+            assert_eq!(csv_row(row, &config).split(',').nth(blank), Some("NA"));
+            assert_eq!(json_cell(&row.0[blank], &config), "\"NA\"");
+        }
+        // the text is quoted like any other field that needs it
+        for flags in &[&["--na", "n/a, \"blank\""][..], &["--na", "n/a, \"blank\"", "--display"][..]] {
+            let config = Config::new(&args(&[&["xlcat", "book.xlsx"][..], flags].concat())).ok().unwrap();
+            assert!(csv_row(row, &config).contains(",\"n/a, \"\"blank\"\"\""));
+        }
+    }
+
+    #[test]
+    fn error_cells() {
+        let mut wb = Workbook::open("tests/data/errors.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let row = sheets.get(1).unwrap().rows(&mut wb).unwrap().next().unwrap();
+        let config = Config::new(&args(&["xlcat", "book.xlsx"])).ok().unwrap();
+        assert!(csv_row(&row, &config).starts_with("\"errors\",#NULL!,#DIV/0!,"));
+        assert_eq!(json_cell(&row.0[2], &config), "\"#DIV/0!\"");
+        let config = Config::new(&args(&["xlcat", "book.xlsx", "--blank-errors"])).ok().unwrap();
+        assert_eq!(csv_row(&row, &config), format!("\"errors\"{}", ",".repeat(11)));
+        assert_eq!(json_cell(&row.0[2], &config), "null");
+        let config = Config::new(&args(&["xlcat", "book.xlsx", "--blank-errors", "--na", "NA"])).ok().unwrap();
+        assert_eq!(json_cell(&row.0[2], &config), "\"NA\"");
+    }
+
+    #[test]
+    fn sheet_manifest() {
+        let config = Config::new(&args(&["xlcat", "book.xlsx", "--manifest"])).ok().unwrap();
+        assert!(config.manifest && config.tab.is_none());
+        let mut wb = Workbook::open("tests/data/sheet_states.xlsx").unwrap();
+        assert_eq!(manifest(&mut wb), concat!(
+            r#"{"date_system":"1904","#,
+            r#""properties":{"creator":"Kevin","created":null,"modified":null,"last_modified_by":null,"#,
+            r#""title":"States","application":null,"company":null},"#,
+            r#""sheets":[{"name":"Shown","position":1,"state":"visible","dimension":"A1:B2","tab_color":"FF00FF00"},"#,
+            r#"{"name":"Hidden","position":2,"state":"hidden","dimension":"A1:B2","tab_color":null},"#,
+            r#"{"name":"Very \"Hidden\"","position":3,"state":"veryHidden","dimension":null,"tab_color":null}]}"#,
+        ));
+    }
+
+    #[test]
+    fn exit_codes() {
+        let run_with = |a: &[&str]| run(&Config::new(&args(a)).ok().unwrap());
+        let missing = run_with(&["xlcat", "no such file.xlsx", "1"]).unwrap_err();
+        assert_eq!(missing.exit_code(), EXIT_NO_FILE);
+        assert!(missing.is_expected());
+        let not_xlsx = run_with(&["xlcat", "Cargo.toml", "1"]).unwrap_err();
+        assert_eq!(not_xlsx.exit_code(), EXIT_BAD_WORKBOOK);
+        assert!(!not_xlsx.is_expected());
+        assert_eq!(run_with(&["xlcat", "tests/data/Book1.xlsx", "Nope"]), Err(RunError::SheetNotFound));
+        assert_eq!(run_with(&["xlcat", "tests/data/empty_sheets.xlsx"]), Err(RunError::NeedTab));
+        assert!(Config::new(&args(&["xlcat", "book.xlsx", "-q"])).ok().unwrap().quiet);
+    }
+
+    #[test]
+    fn version_is_package_version() {
+        // read the version out of the manifest itself rather than trusting what cargo passed on
+        let manifest = include_str!("../../Cargo.toml");
+        let version = manifest.lines()
+            .find_map(|line| line.strip_prefix("version = "))
+            .unwrap()
+            .trim_matches('"');
+        assert_eq!(version_string(), format!("xlcat {}", version));
+        assert!(Config::new(&args(&["xlcat", "--version"])).ok().unwrap().want_version);
+    }
+}
@@ -0,0 +1,247 @@
+//! This module reads the comments attached to the cells of a worksheet. Workbooks can hold two
+//! kinds:
+//!
+//! - notes (what older versions of Excel called comments), which a worksheet points at through a
+//!   "comments" relationship (e.g., "xl/comments1.xml"), and
+//! - threaded comments (what newer versions of Excel call comments), which a worksheet points at
+//!   through a "threadedComment" relationship (e.g., "xl/threadedComments/threadedComment1.xml").
+//!   Their authors are listed separately in the workbook's person list (e.g.,
+//!   "xl/persons/person.xml").
+//!
+//! When Excel saves a threaded comment, it also saves a note in the same cell that describes the
+//! thread for older versions of Excel. We only return the threaded comment in that case.
+
+use std::collections::HashMap;
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use crate::utils::{self, CellRef, TextDecoder};
+use crate::wb::Workbook;
+
+/// A comment (or note) attached to a cell of a worksheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// The cell the comment is attached to
+    pub anchor: CellRef,
+    /// Who wrote the comment (empty if the workbook does not say)
+    pub author: String,
+    /// The text of the comment. Excel starts the text of a note with the name of its author
+    /// (e.g., "Kevin Ryan:\n..."), and that is left as it is.
+    pub text: String,
+    /// Is this a threaded comment (rather than a note)?
+    pub threaded: bool,
+    /// The replies to a threaded comment, oldest first (notes never have any)
+    pub replies: Vec<Comment>,
+}
+
+/// Return every comment in the worksheet at `sheet_part`, ordered by the row and then the column
+/// of the cell each one is attached to.
+pub(crate) fn comments(workbook: &mut Workbook, sheet_part: &str) -> Vec<Comment> {
+    let rels = workbook.part_rels(sheet_part);
+    let mut threaded = Vec::new();
+    let mut notes = Vec::new();
+    let mut people = None;
+    for rel in &rels {
+        let is_thread = rel.kind.ends_with("/threadedComment");
+        if !is_thread && !rel.kind.ends_with("/comments") {
+            continue
+        }
+        let xml = match workbook.read_part(&rel.target) {
+            Some(xml) => xml,
+            None => continue,
+        };
+        if is_thread {
+            let people = people.get_or_insert_with(|| people_by_id(workbook));
+            threaded.extend(read_threads(&xml, people));
+        } else {
+            notes.extend(read_notes(&xml));
+        }
+    }
+    // a note in a cell that has a threaded comment only describes the thread for older versions of
+    // Excel
+    notes.retain(|note| !threaded.iter().any(|c| c.anchor == note.anchor));
+    let mut comments = threaded;
+    comments.extend(notes);
+    comments.sort_by_key(|c| (c.anchor.row, c.anchor.col));
+    comments
+}
+
+/// The names of the people who wrote the threaded comments in the workbook, by their id.
+fn people_by_id(workbook: &mut Workbook) -> HashMap<String, String> {
+    let mut people = HashMap::new();
+    let main_part = workbook.main_part.clone();
+    let lists = workbook.part_rels(&main_part).into_iter().filter(|r| r.kind.ends_with("/person"));
+    for list in lists {
+        let xml = match workbook.read_part(&list.target) {
+            Some(xml) => xml,
+            None => continue,
+        };
+        let mut reader = Reader::from_reader(utils::skip_bom(&xml[..]));
+        let mut decoder = TextDecoder::utf8();
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Decl(ref e)) => decoder.declared(e),
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.local_name() == b"person" => {
+                    let mut id = None;
+                    let mut name = String::new();
+                    for a in e.attributes().flatten() {
+                        match a.key {
+                            b"id" => id = Some(utils::attr_value(&a)),
+                            b"displayName" => name = decoder.unescape(&a.value),
+                            _ => (),
+                        }
+                    }
+                    if let Some(id) = id {
+                        people.insert(id, name);
+                    }
+                },
+                Ok(Event::Eof) | Err(_) => break,
+                _ => (),
+            }
+            buf.clear();
+        }
+    }
+    people
+}
+
+/// The cell a `ref` attribute (e.g., "B2") points at
+fn anchor(reference: &str) -> Option<CellRef> {
+    let (_, col, _, row) = utils::parse_cell_reference(reference)?;
+    Some(CellRef::new(col, row))
+}
+
+/// Read the notes in a comments part (e.g., "xl/comments1.xml").
+fn read_notes(xml: &[u8]) -> Vec<Comment> {
+    // Looking for tree structure like:
+    //   authors
+    //     author
+    //   commentList
+    //     comment(ref = "A1", authorId = "0")
+    //       text
+    //         t (or several r elements, each with a t of its own)
+    //
+    // Whitespace is significant in the text, so we do not trim it (and only keep the text that
+    // is inside a t element).
+    let mut reader = Reader::from_reader(utils::skip_bom(xml));
+    let mut decoder = TextDecoder::utf8();
+    let mut authors = Vec::new();
+    let mut notes = Vec::new();
+    let mut buf = Vec::new();
+    let mut in_author = false;
+    let mut in_text = false;
+    // phonetic guides (rPh) hold text too, but it is not part of the comment
+    let mut in_phonetic = false;
+    let mut current: Option<(Comment, usize)> = None;
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Decl(ref e)) => decoder.declared(e),
+            Ok(Event::Start(ref e)) => match e.local_name() {
+                b"author" => {
+                    in_author = true;
+                    authors.push(String::new());
+                },
+                b"comment" => {
+                    let author = utils::get(e.attributes(), b"authorId").and_then(|id| id.parse().ok());
+                    current = utils::get(e.attributes(), b"ref").and_then(|r| anchor(&r)).map(|anchor| {
+                        let comment = Comment { anchor, author: String::new(), text: String::new(), threaded: false, replies: vec![] };
+                        (comment, author.unwrap_or(usize::MAX))
+                    });
+                },
+                b"t" => in_text = true,
+                b"rPh" => in_phonetic = true,
+                _ => (),
+            },
+            Ok(Event::Text(ref e)) if in_author => {
+                if let Some(author) = authors.last_mut() {
+                    author.push_str(&decoder.unescape(e.escaped()));
+                }
+            },
+            Ok(Event::Text(ref e)) if in_text && !in_phonetic => {
+                if let Some((comment, _)) = current.as_mut() {
+                    comment.text.push_str(&decoder.unescape(e.escaped()));
+                }
+            },
+            Ok(Event::End(ref e)) => match e.local_name() {
+                b"author" => in_author = false,
+                b"t" => in_text = false,
+                b"rPh" => in_phonetic = false,
+                b"comment" => {
+                    if let Some((mut comment, author)) = current.take() {
+                        comment.author = authors.get(author).cloned().unwrap_or_default();
+                        notes.push(comment);
+                    }
+                },
+                _ => (),
+            },
+            Ok(Event::Eof) | Err(_) => break,
+            _ => (),
+        }
+        buf.clear();
+    }
+    notes
+}
+
+/// Read the threaded comments in a threadedComments part, with each reply under the comment that
+/// started its thread. `people` gives the name of each author by their id.
+fn read_threads(xml: &[u8], people: &HashMap<String, String>) -> Vec<Comment> {
+    // Looking for tree structure like:
+    //   threadedComment(ref = "B2", personId = "{...}", id = "{...}", parentId = "{...}")
+    //     text
+    //
+    // Replies have a parentId (the id of the first comment in the thread); the first comment
+    // does not.
+    let mut reader = Reader::from_reader(utils::skip_bom(xml));
+    let mut decoder = TextDecoder::utf8();
+    let mut threads: Vec<(String, Comment)> = Vec::new();
+    let mut buf = Vec::new();
+    let mut in_text = false;
+    let mut current: Option<(Comment, String, Option<String>)> = None;
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Decl(ref e)) => decoder.declared(e),
+            Ok(Event::Start(ref e)) if e.local_name() == b"threadedComment" => {
+                let mut reference = None;
+                let mut author = String::new();
+                let mut id = String::new();
+                let mut parent = None;
+                for a in e.attributes().flatten() {
+                    match a.key {
+                        b"ref" => reference = anchor(&utils::attr_value(&a)),
+                        b"personId" => author = people.get(&utils::attr_value(&a)).cloned().unwrap_or_default(),
+                        b"id" => id = utils::attr_value(&a),
+                        b"parentId" => parent = Some(utils::attr_value(&a)),
+                        _ => (),
+                    }
+                }
+                current = reference.map(|anchor| {
+                    (Comment { anchor, author, text: String::new(), threaded: true, replies: vec![] }, id, parent)
+                });
+            },
+            Ok(Event::Start(ref e)) if e.local_name() == b"text" => in_text = true,
+            Ok(Event::Text(ref e)) if in_text => {
+                if let Some((comment, _, _)) = current.as_mut() {
+                    comment.text.push_str(&decoder.unescape(e.escaped()));
+                }
+            },
+            Ok(Event::End(ref e)) => match e.local_name() {
+                b"text" => in_text = false,
+                b"threadedComment" => match current.take() {
+                    Some((comment, _, Some(parent))) => {
+                        match threads.iter_mut().find(|(id, _)| *id == parent) {
+                            Some((_, thread)) => thread.replies.push(comment),
+                            // a reply whose thread is gone is kept on its own
+                            None => threads.push((String::new(), comment)),
+                        }
+                    },
+                    Some((comment, id, None)) => threads.push((id, comment)),
+                    None => (),
+                },
+                _ => (),
+            },
+            Ok(Event::Eof) | Err(_) => break,
+            _ => (),
+        }
+        buf.clear();
+    }
+    threads.into_iter().map(|(_, comment)| comment).collect()
+}
@@ -0,0 +1,128 @@
+//! This module reads the drawings attached to a worksheet. A worksheet points at a drawing part
+//! (e.g., "xl/drawings/drawing1.xml") through its relationships, the drawing says which cell each
+//! picture is anchored to, and the drawing's own relationships point at the image files (e.g.,
+//! "xl/media/image1.png").
+
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use crate::utils::{self, CellRef};
+use crate::wb::Workbook;
+
+/// An image embedded in a worksheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    /// The cell the top-left corner of the image is anchored to. Images that are positioned
+    /// absolutely (rather than relative to a cell) are anchored to A1.
+    pub anchor: CellRef,
+    /// The raw contents of the image file
+    pub bytes: Vec<u8>,
+    /// The MIME type of the image (based on its file extension), e.g., "image/png"
+    pub mime: String,
+}
+
+/// Return every image embedded in the worksheet at `sheet_part` (in the order the drawings list
+/// them).
+pub(crate) fn images(workbook: &mut Workbook, sheet_part: &str) -> Vec<Image> {
+    let mut images = Vec::new();
+    let drawings = workbook.part_rels(sheet_part).into_iter().filter(|r| r.kind.ends_with("/drawing"));
+    for drawing in drawings {
+        let xml = match workbook.read_part(&drawing.target) {
+            Some(xml) => xml,
+            None => continue,
+        };
+        let media = workbook.part_rels(&drawing.target);
+        for (anchor, embed) in anchors(&xml) {
+            let target = match media.iter().find(|r| r.id == embed) {
+                Some(rel) => &rel.target,
+                None => continue,
+            };
+            if let Some(bytes) = workbook.read_part(target) {
+                images.push(Image { anchor, bytes, mime: mime_type(target).to_owned() });
+            }
+        }
+    }
+    images
+}
+
+/// Find the pictures in a drawing: the cell each one is anchored to, along with the id of the
+/// relationship that points to its image file.
+fn anchors(xml: &[u8]) -> Vec<(CellRef, String)> {
+    // Looking for tree structure like:
+    //   twoCellAnchor (or oneCellAnchor / absoluteAnchor)
+    //     from
+    //       col (0-based)
+    //       row (0-based)
+    //     pic
+    //       blipFill
+    //         blip(embed = "rId1")
+    let mut reader = Reader::from_reader(utils::skip_bom(xml));
+    reader.trim_text(true);
+    let mut anchors = Vec::new();
+    let mut buf = Vec::new();
+    let mut anchor = CellRef::new(1, 1);
+    let mut embed = None;
+    let mut in_from = false;
+    let mut field = None;
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Start(ref e)) => match e.local_name() {
+                b"twoCellAnchor" | b"oneCellAnchor" | b"absoluteAnchor" => {
+                    anchor = CellRef::new(1, 1);
+                    embed = None;
+                },
+                b"from" => in_from = true,
+                name @ b"col" | name @ b"row" if in_from => field = Some(name.to_vec()),
+                b"blip" => embed = embed.or_else(|| blip_embed(e)),
+                _ => (),
+            },
+            Ok(Event::Empty(ref e)) if e.local_name() == b"blip" => embed = embed.or_else(|| blip_embed(e)),
+            Ok(Event::Text(ref e)) => {
+                if let Some(name) = field.take() {
+                    let n: u32 = String::from_utf8_lossy(e.escaped()).parse().unwrap_or(0);
+                    if name == b"col" {
+                        anchor.col = n as u16 + 1;
+                    } else {
+                        anchor.row = n + 1;
+                    }
+                }
+            },
+            Ok(Event::End(ref e)) => match e.local_name() {
+                b"from" => in_from = false,
+                b"col" | b"row" => field = None,
+                b"twoCellAnchor" | b"oneCellAnchor" | b"absoluteAnchor" => {
+                    if let Some(id) = embed.take() {
+                        anchors.push((anchor.clone(), id));
+                    }
+                },
+                _ => (),
+            },
+            Ok(Event::Eof) | Err(_) => break,
+            _ => (),
+        }
+        buf.clear();
+    }
+    anchors
+}
+
+/// The relationship id in a `<a:blip r:embed="rId1"/>` element
+fn blip_embed(e: &quick_xml::events::BytesStart) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key == b"embed" || a.key.ends_with(b":embed"))
+        .map(|a| String::from_utf8_lossy(&a.value).into_owned())
+}
+
+fn mime_type(part: &str) -> &'static str {
+    let extension = part.rsplit('.').next().unwrap_or("").to_ascii_lowercase();
+    match &extension[..] {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "bmp" => "image/bmp",
+        "tif" | "tiff" => "image/tiff",
+        "svg" => "image/svg+xml",
+        "emf" => "image/x-emf",
+        "wmf" => "image/x-wmf",
+        _ => "application/octet-stream",
+    }
+}
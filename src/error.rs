@@ -0,0 +1,62 @@
+//! The errors you can get back while reading a workbook.
+
+use std::error::Error;
+use std::fmt;
+
+/// Something went wrong while reading a workbook.
+#[derive(Debug, PartialEq)]
+pub enum XlError {
+    /// The workbook could not be opened (e.g., the file does not exist or is not a zip)
+    Open(String),
+    /// The workbook refers to a part (e.g., the xml file holding a worksheet) that is not in the
+    /// xlsx file. This usually means a relationship in the workbook points at a part that was
+    /// removed or never written.
+    MissingPart(String),
+    /// An option you asked for (e.g., in `ReadOptions`) does not make sense
+    BadOption(String),
+    /// The file is an old-style Excel workbook (a BIFF ".xls" file stored in an OLE compound
+    /// file) rather than an xlsx file. This crate only reads xlsx files.
+    LegacyXls(String),
+    /// The file is an Office package, but not a spreadsheet (e.g., a Word document or a
+    /// PowerPoint presentation). This holds the path and the content type of the package's main
+    /// part.
+    NotSpreadsheet(String, String),
+    /// The xml of a part is malformed. This holds the position in the part where the parser gave
+    /// up and what the parser said was wrong.
+    Xml(usize, String),
+}
+
+impl fmt::Display for XlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            XlError::Open(msg) => write!(f, "{}", msg),
+            XlError::MissingPart(part) => write!(f, "could not find {} in the workbook", part),
+            XlError::BadOption(msg) => write!(f, "{}", msg),
+            XlError::LegacyXls(path) => write!(
+                f,
+                "'{}' is an old-style .xls workbook, but only xlsx files can be read (open it in Excel and save it as .xlsx first)",
+                path
+            ),
+            XlError::NotSpreadsheet(path, content_type) => write!(
+                f,
+                "'{}' is not a spreadsheet{} (its main part is {})",
+                path, describe(content_type), content_type
+            ),
+            XlError::Xml(position, msg) => write!(f, "bad xml at position {}: {}", position, msg),
+        }
+    }
+}
+
+impl Error for XlError {}
+
+/// What kind of document a main part with the given content type makes (e.g., ", but a Word
+/// document"), for the packages people are most likely to open by mistake
+fn describe(content_type: &str) -> &'static str {
+    if content_type.contains("wordprocessingml") || content_type.contains("ms-word") {
+        ", but a Word document"
+    } else if content_type.contains("presentationml") || content_type.contains("ms-powerpoint") {
+        ", but a PowerPoint presentation"
+    } else {
+        ""
+    }
+}
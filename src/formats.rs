@@ -0,0 +1,575 @@
+//! This module knows how to apply an Excel number format (e.g., "#,##0.00" or "d-mmm-yy") to a
+//! value, so you can show a value the way it looks in Excel. A format code has up to four sections
+//! separated by semicolons (for positive numbers, negative numbers, zero, and text), and each
+//! section is broken into `Part`s before we render anything.
+//!
+//! Not everything Excel supports is handled here. Conditions (e.g., "[>100]") and colors are
+//! ignored, and fractions (e.g., "# ?/?") are shown as if the format were "General".
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+use crate::utils;
+use crate::wb::DateSystem;
+use crate::ws::ExcelValue;
+
+const MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June",
+    "July", "August", "September", "October", "November", "December",
+];
+
+const DAYS: [&str; 7] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+
+/// The pieces a section of a format code is made of
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    /// Text that is shown as is
+    Literal(String),
+    /// A digit placeholder: '0', '#', or '?'
+    Digit(char),
+    Decimal,
+    Comma,
+    Percent,
+    /// The 'E' in scientific notation (true if the exponent always shows its sign)
+    Exponent(bool),
+    /// '@' (where the text goes in a text section)
+    Text,
+    General,
+    Year(usize),
+    Month(usize),
+    Day(usize),
+    Hour(usize),
+    Minute(usize),
+    Second(usize),
+    /// Digits of a fraction of a second (e.g., the "00" in "ss.00")
+    SubSecond(usize),
+    /// Hours, minutes, or seconds in brackets (e.g., "[h]"), which do not roll over
+    Elapsed(char, usize),
+    /// AM/PM (true) or A/P (false)
+    AmPm(bool),
+}
+
+impl ExcelValue<'_> {
+    /// Format this value the way Excel would show it with the number format `code` (e.g., what
+    /// you find in `Cell::style`).
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::ExcelValue;
+    ///
+    ///     assert_eq!(ExcelValue::Number(1234.5).format("#,##0.00"), "1,234.50");
+    ///     assert_eq!(ExcelValue::Number(-3.0).format("0;(0)"), "(3)");
+    ///     assert_eq!(ExcelValue::Number(0.25).format("0%"), "25%");
+    ///     assert_eq!(ExcelValue::Number(43831.0).format("d-mmm-yy"), "1-Jan-20");
+    pub fn format(&self, code: &str) -> String {
+        let sections: Vec<Vec<Part>> = split_sections(code).iter().map(|s| parse_section(s)).collect();
+        let serial = |dt: NaiveDateTime| utils::date_to_excel_number(dt, &DateSystem::V1900);
+        match self {
+            ExcelValue::None => String::new(),
+            ExcelValue::Bool(true) => "TRUE".to_owned(),
+            ExcelValue::Bool(false) => "FALSE".to_owned(),
+            ExcelValue::Error(e) => e.to_string(),
+            ExcelValue::String(s) => format_text(&sections, s),
+            ExcelValue::Number(n) => format_number(&sections, *n),
+            ExcelValue::Date(d) => format_number(&sections, serial(d.and_hms_opt(0, 0, 0).unwrap())),
+            ExcelValue::DateTime(d) => format_number(&sections, serial(*d)),
+            ExcelValue::Time(t) => format_number(&sections, utils::time_to_excel_number(*t)),
+        }
+    }
+}
+
+/// Split a format code into its sections (at semicolons that are not quoted or escaped)
+fn split_sections(code: &str) -> Vec<String> {
+    let mut sections = vec![String::new()];
+    let mut chars = code.chars();
+    let mut in_quotes = false;
+    while let Some(c) = chars.next() {
+        let section = sections.last_mut().unwrap();
+        match c {
+            '"' => { in_quotes = !in_quotes; section.push(c) },
+            '\\' if !in_quotes => {
+                section.push(c);
+                if let Some(next) = chars.next() { section.push(next) }
+            },
+            ';' if !in_quotes => sections.push(String::new()),
+            _ => section.push(c),
+        }
+    }
+    sections
+}
+
+fn parse_section(section: &str) -> Vec<Part> {
+    let chars: Vec<char> = section.chars().collect();
+    let mut parts = Vec::new();
+    let mut i = 0;
+    let run = |i: usize, c: char| chars[i..].iter().take_while(|x| x.eq_ignore_ascii_case(&c)).count();
+    let starts_with = |i: usize, s: &str| {
+        let s: Vec<char> = s.chars().collect();
+        chars.len() >= i + s.len() && chars[i..i + s.len()].iter().zip(&s).all(|(a, b)| a.eq_ignore_ascii_case(b))
+    };
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        match c {
+            '"' => {
+                let text: String = chars[i..].iter().take_while(|&&x| x != '"').collect();
+                i += text.chars().count() + 1;
+                parts.push(Part::Literal(text));
+            },
+            '\\' => {
+                if let Some(&next) = chars.get(i) { parts.push(Part::Literal(next.to_string())) }
+                i += 1;
+            },
+            // "_x" leaves a space as wide as x, and "*x" repeats x to fill the cell
+            '_' => { parts.push(Part::Literal(" ".to_owned())); i += 1 },
+            '*' => i += 1,
+            '[' => {
+                let inside: String = chars[i..].iter().take_while(|&&x| x != ']').collect();
+                i += inside.chars().count() + 1;
+                let lower = inside.to_lowercase();
+                if let Some(currency) = inside.strip_prefix('$') {
+                    // e.g., [$€-407] is the euro sign (and a locale we ignore)
+                    let symbol = currency.split('-').next().unwrap_or("");
+                    parts.push(Part::Literal(symbol.to_owned()));
+                } else if !lower.is_empty() && ['h', 'm', 's'].iter().any(|&u| lower.chars().all(|x| x == u)) {
+                    parts.push(Part::Elapsed(lower.chars().next().unwrap(), lower.len()));
+                }
+                // colors and conditions are ignored
+            },
+            '0' | '#' | '?' => parts.push(Part::Digit(c)),
+            '.' => parts.push(Part::Decimal),
+            ',' => parts.push(Part::Comma),
+            '%' => parts.push(Part::Percent),
+            '@' => parts.push(Part::Text),
+            'E' | 'e' if matches!(chars.get(i), Some('+') | Some('-')) => {
+                parts.push(Part::Exponent(chars[i] == '+'));
+                i += 1;
+            },
+            _ if starts_with(i - 1, "general") => { parts.push(Part::General); i += 6 },
+            _ if starts_with(i - 1, "am/pm") => { parts.push(Part::AmPm(true)); i += 4 },
+            _ if starts_with(i - 1, "a/p") => { parts.push(Part::AmPm(false)); i += 2 },
+            'y' | 'Y' | 'm' | 'M' | 'd' | 'D' | 'h' | 'H' | 's' | 'S' => {
+                let len = run(i - 1, c);
+                i += len - 1;
+                parts.push(match c.to_ascii_lowercase() {
+                    'y' => Part::Year(len),
+                    'm' => Part::Month(len),
+                    'd' => Part::Day(len),
+                    'h' => Part::Hour(len),
+                    _ => Part::Second(len),
+                });
+            },
+            _ => parts.push(Part::Literal(c.to_string())),
+        }
+    }
+    resolve_dates(parts)
+}
+
+/// Sort out the parts whose meaning depends on what is around them in a date format: "m" means
+/// minutes right after hours or right before seconds, and "0"s after the seconds are fractions
+/// of a second.
+fn resolve_dates(mut parts: Vec<Part>) -> Vec<Part> {
+    if !parts.iter().any(is_date_part) {
+        return parts
+    }
+    let date_positions: Vec<usize> = (0..parts.len()).filter(|&i| is_date_part(&parts[i])).collect();
+    for (n, &i) in date_positions.iter().enumerate() {
+        if let Part::Month(len) = parts[i] {
+            let after_hour = n > 0 && matches!(parts[date_positions[n - 1]], Part::Hour(_) | Part::Elapsed('h', _));
+            let before_second = matches!(date_positions.get(n + 1).map(|&j| &parts[j]), Some(Part::Second(_)) | Some(Part::Elapsed('s', _)));
+            if after_hour || before_second {
+                parts[i] = Part::Minute(len);
+            }
+        }
+    }
+    let mut resolved = Vec::with_capacity(parts.len());
+    let mut iter = parts.into_iter().peekable();
+    while let Some(part) = iter.next() {
+        if part == Part::Decimal && matches!(resolved.last(), Some(Part::Second(_)) | Some(Part::Elapsed('s', _))) {
+            let mut len = 0;
+            while iter.peek() == Some(&Part::Digit('0')) {
+                iter.next();
+                len += 1;
+            }
+            resolved.push(if len > 0 { Part::SubSecond(len) } else { Part::Literal(".".to_owned()) });
+        } else {
+            resolved.push(part);
+        }
+    }
+    resolved
+}
+
+fn is_date_part(part: &Part) -> bool {
+    matches!(part, Part::Year(_) | Part::Month(_) | Part::Day(_) | Part::Hour(_) | Part::Minute(_)
+        | Part::Second(_) | Part::Elapsed(..) | Part::AmPm(_))
+}
+
+fn format_text(sections: &[Vec<Part>], text: &str) -> String {
+    let section = match sections.len() {
+        4 => &sections[3],
+        1 if sections[0].contains(&Part::Text) => &sections[0],
+        _ => return text.to_owned(),
+    };
+    section.iter().map(|part| match part {
+        Part::Text => text.to_owned(),
+        Part::Literal(s) => s.clone(),
+        _ => String::new(),
+    }).collect()
+}
+
+fn format_number(sections: &[Vec<Part>], n: f64) -> String {
+    // Excel cannot hold infinity or NaN, and the error it gives instead is #NUM!
+    if !n.is_finite() {
+        return "#NUM!".to_owned()
+    }
+    // pick the section for this number; only the first section shows a minus sign itself
+    let (section, n) = match sections.len() {
+        1 => (&sections[0], n),
+        2 if n < 0.0 => (&sections[1], -n),
+        2 => (&sections[0], n),
+        _ if n < 0.0 => (&sections[1], -n),
+        _ if n == 0.0 => (&sections[2], n),
+        _ => (&sections[0], n),
+    };
+    // an empty section hides the number (e.g., zeros in "0;-0;;@"), unless the whole code is empty
+    if section.is_empty() && sections.len() > 1 {
+        return String::new()
+    }
+    if section.iter().any(is_date_part) {
+        return format_date(section, n)
+    }
+    let has_digits = section.iter().any(|p| matches!(p, Part::Digit(_)));
+    let is_fraction = section.contains(&Part::Literal("/".to_owned())) && has_digits;
+    if section.contains(&Part::General) || is_fraction || section.is_empty() {
+        return general(n)
+    }
+    // a text format (e.g., "@") shows a number the way General does
+    if section.contains(&Part::Text) && !has_digits {
+        return section.iter().map(|part| match part {
+            Part::Text => general(n),
+            Part::Literal(s) => s.clone(),
+            _ => String::new(),
+        }).collect()
+    }
+    format_digits(section, n)
+}
+
+/// Excel's "General" format: up to 10 significant digits and scientific notation for very big or
+/// very small numbers
+fn general(n: f64) -> String {
+    if n == 0.0 {
+        return "0".to_owned()
+    }
+    let magnitude = n.abs().log10().floor() as i32;
+    if !(-9..11).contains(&magnitude) {
+        let s = format!("{:.5E}", n);
+        let (mantissa, exponent) = s.split_once('E').unwrap();
+        let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+        let exponent: i32 = exponent.parse().unwrap();
+        return format!("{}E{}{:02}", mantissa, if exponent < 0 { '-' } else { '+' }, exponent.abs())
+    }
+    let decimals = (9 - magnitude).max(0) as usize;
+    let s = format!("{:.*}", decimals, n);
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.').to_owned()
+    } else {
+        s
+    }
+}
+
+#[allow(clippy::unnecessary_map_or)] // `is_none_or` needs Rust 1.82
+fn format_digits(section: &[Part], mut n: f64) -> String {
+    let negative = n < 0.0;
+    n = n.abs();
+    let exponent_at = section.iter().position(|p| matches!(p, Part::Exponent(_)));
+    let mantissa_parts = &section[..exponent_at.unwrap_or(section.len())];
+    let decimal_at = mantissa_parts.iter().position(|p| *p == Part::Decimal).unwrap_or(mantissa_parts.len());
+    let int_digits: Vec<usize> = (0..decimal_at).filter(|&i| matches!(section[i], Part::Digit(_))).collect();
+    let frac_digits: Vec<usize> = (decimal_at..mantissa_parts.len()).filter(|&i| matches!(section[i], Part::Digit(_))).collect();
+
+    // a comma between integer digits turns on thousands separators; commas right after the last
+    // digit divide the number by 1000 each
+    let last_int = int_digits.last().copied();
+    let last_digit = frac_digits.last().copied().or(last_int);
+    let mut grouping = false;
+    for (i, part) in mantissa_parts.iter().enumerate() {
+        if *part != Part::Comma { continue }
+        match (last_int, last_digit) {
+            (Some(last), _) if i < last => grouping = true,
+            (_, Some(last)) if i > last && section[last + 1..i].iter().all(|p| *p == Part::Comma) => n /= 1000.0,
+            _ => (),
+        }
+    }
+    for _ in section.iter().filter(|p| **p == Part::Percent) {
+        n *= 100.0;
+    }
+
+    let mut exponent = 0;
+    if exponent_at.is_some() && n != 0.0 {
+        let int_len = int_digits.len().max(1) as i32;
+        exponent = n.log10().floor() as i32;
+        let engineering = int_len > 1 && int_digits.iter().any(|&i| section[i] == Part::Digit('#'));
+        exponent -= if engineering { exponent.rem_euclid(int_len) } else { int_len - 1 };
+        n /= 10f64.powi(exponent);
+        // rounding can carry the mantissa up to the next power of ten (9.999 becomes "10.00")
+        let rounded: f64 = format!("{:.*}", frac_digits.len(), n).parse().unwrap();
+        if rounded >= 10f64.powi(int_len) {
+            let step = if engineering { int_len } else { 1 };
+            n /= 10f64.powi(step);
+            exponent += step;
+        }
+    }
+
+    let number = format!("{:.*}", frac_digits.len(), n);
+    let (int_str, frac_str) = number.split_once('.').unwrap_or((&number, ""));
+    let mut int_str = if int_str == "0" { String::new() } else { int_str.to_owned() };
+    let min_int = int_digits.iter().filter(|&&i| section[i] == Part::Digit('0')).count();
+    if grouping {
+        while int_str.len() < min_int { int_str.insert(0, '0') }
+        int_str = group_thousands(&int_str);
+    }
+
+    // the fraction digits: trailing zeros disappear for '#' and become spaces for '?'
+    let mut frac: Vec<char> = frac_str.chars().collect();
+    for (pos, &i) in frac_digits.iter().enumerate().rev() {
+        if frac[pos] != '0' { break }
+        match section[i] {
+            Part::Digit('#') => { frac.pop(); },
+            Part::Digit('?') => frac[pos] = ' ',
+            _ => break,
+        }
+    }
+
+    // hand out the integer digits from the right, one per placeholder (the leftmost placeholder
+    // gets whatever is left over)
+    let mut int_out = vec![String::new(); section.len()];
+    let mut remaining: Vec<char> = int_str.chars().collect();
+    for (n, &i) in int_digits.iter().enumerate().rev() {
+        if grouping {
+            if n == 0 { int_out[i] = remaining.iter().collect() }
+            continue
+        }
+        if n == 0 {
+            let rest: String = remaining.drain(..).collect();
+            int_out[i] = if rest.is_empty() { placeholder_fill(&section[i]) } else { rest };
+        } else {
+            int_out[i] = match remaining.pop() {
+                Some(d) => d.to_string(),
+                None => placeholder_fill(&section[i]),
+            };
+        }
+    }
+
+    let mut out = String::new();
+    if negative && !number.chars().all(|c| c == '0' || c == '.') {
+        out.push('-');
+    }
+    let mut frac_pos = 0;
+    for (i, part) in section.iter().enumerate() {
+        match part {
+            Part::Digit(_) if i < decimal_at => out.push_str(&int_out[i]),
+            Part::Digit(_) if exponent_at.map_or(true, |e| i < e) => {
+                if let Some(c) = frac.get(frac_pos) { out.push(*c) }
+                frac_pos += 1;
+            },
+            Part::Digit(_) => (),
+            Part::Decimal if i == decimal_at => out.push('.'),
+            Part::Decimal => (),
+            Part::Percent => out.push('%'),
+            Part::Literal(s) => out.push_str(s),
+            Part::Exponent(plus) => {
+                out.push('E');
+                if exponent < 0 { out.push('-') } else if *plus { out.push('+') }
+                let width = section[i + 1..].iter().filter(|p| matches!(p, Part::Digit(_))).count();
+                out.push_str(&format!("{:0width$}", exponent.abs(), width = width));
+            },
+            _ => (),
+        }
+    }
+    out
+}
+
+/// What an integer placeholder shows when it has no digit of its own
+fn placeholder_fill(part: &Part) -> String {
+    match part {
+        Part::Digit('0') => "0".to_owned(),
+        Part::Digit('?') => " ".to_owned(),
+        _ => String::new(),
+    }
+}
+
+#[allow(clippy::manual_is_multiple_of)] // `is_multiple_of` needs Rust 1.87
+fn group_thousands(digits: &str) -> String {
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn format_date(section: &[Part], serial: f64) -> String {
+    // Excel fills the cell with #s for a negative date or one after 9999-12-31
+    if !(0.0..2958466.0).contains(&serial) {
+        return "#####".to_owned()
+    }
+    let sub_second_digits = section.iter().find_map(|p| match p {
+        Part::SubSecond(len) => Some(*len),
+        _ => None,
+    }).unwrap_or(0);
+    // round to the precision the format shows (Excel rounds rather than truncates)
+    let unit = 10f64.powi(sub_second_digits as i32);
+    let ticks = (serial * 86400.0 * unit).round();
+    let total_seconds = (ticks / unit).floor() as i64;
+    let fraction = ticks as i64 - total_seconds * unit as i64;
+    let days = total_seconds.div_euclid(86400);
+    let seconds = total_seconds.rem_euclid(86400);
+    // serial 1 is 1900-01-01, and Excel thinks 1900 was a leap year (serial 60 is 1900-02-29)
+    let base = if days < 61 { NaiveDate::from_ymd_opt(1899, 12, 31).unwrap() } else { NaiveDate::from_ymd_opt(1899, 12, 30).unwrap() };
+    let datetime = base.and_hms_opt(0, 0, 0).unwrap() + Duration::days(days) + Duration::seconds(seconds);
+    let twelve_hour = section.iter().any(|p| matches!(p, Part::AmPm(_)));
+    let pad = |n: u32, len: usize| if len >= 2 { format!("{:02}", n) } else { n.to_string() };
+
+    let mut out = String::new();
+    for part in section {
+        match part {
+            Part::Literal(s) => out.push_str(s),
+            Part::Year(len) if *len <= 2 => out.push_str(&format!("{:02}", datetime.year() % 100)),
+            Part::Year(_) => out.push_str(&datetime.year().to_string()),
+            Part::Month(len) => {
+                let name = MONTHS[datetime.month0() as usize];
+                match len {
+                    1 | 2 => out.push_str(&pad(datetime.month(), *len)),
+                    3 => out.push_str(&name[..3]),
+                    5 => out.push_str(&name[..1]),
+                    _ => out.push_str(name),
+                }
+            },
+            Part::Day(len) if *len <= 2 => out.push_str(&pad(datetime.day(), *len)),
+            Part::Day(3) => out.push_str(&DAYS[datetime.weekday().num_days_from_monday() as usize][..3]),
+            Part::Day(_) => out.push_str(DAYS[datetime.weekday().num_days_from_monday() as usize]),
+            Part::Hour(len) => {
+                let hour = if twelve_hour {
+                    match datetime.hour() % 12 { 0 => 12, h => h }
+                } else {
+                    datetime.hour()
+                };
+                out.push_str(&pad(hour, *len));
+            },
+            Part::Minute(len) => out.push_str(&pad(datetime.minute(), *len)),
+            Part::Second(len) => out.push_str(&pad(datetime.second(), *len)),
+            Part::SubSecond(len) => out.push_str(&format!(".{:0width$}", fraction, width = len)),
+            Part::Elapsed(unit, len) => {
+                let total = match unit {
+                    'h' => total_seconds.div_euclid(3600),
+                    'm' => total_seconds.div_euclid(60),
+                    _ => total_seconds,
+                };
+                out.push_str(&format!("{:0width$}", total, width = len));
+            },
+            Part::AmPm(full) => {
+                let am = datetime.hour() < 12;
+                out.push_str(match (full, am) {
+                    (true, true) => "AM",
+                    (true, false) => "PM",
+                    (false, true) => "A",
+                    (false, false) => "P",
+                });
+            },
+            Part::Digit(c) => out.push(*c),
+            Part::Decimal => out.push('.'),
+            Part::Comma => out.push(','),
+            Part::Percent => out.push('%'),
+            _ => (),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::ws::{CellError, ExcelValue};
+
+    fn num(n: f64, code: &str) -> String {
+        ExcelValue::Number(n).format(code)
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(num(1234.567, "General"), "1234.567");
+        assert_eq!(num(1234.567, "0"), "1235");
+        assert_eq!(num(1234.567, "0.00"), "1234.57");
+        assert_eq!(num(1234.567, "#,##0"), "1,235");
+        assert_eq!(num(1234567.891, "#,##0.00"), "1,234,567.89");
+        assert_eq!(num(0.5, "#.00"), ".50");
+        assert_eq!(num(5.0, "000"), "005");
+        assert_eq!(num(1.5, "0.0#"), "1.5");
+        assert_eq!(num(1.25, "0.0#"), "1.25");
+        assert_eq!(num(-1234.5, "#,##0.00"), "-1,234.50");
+        assert_eq!(num(123456789.0, "000-00-0000"), "123-45-6789");
+        assert_eq!(num(1500000.0, "0.0,,\"M\""), "1.5M");
+    }
+
+    #[test]
+    fn sections() {
+        assert_eq!(num(-1234.0, "#,##0 ;(#,##0)"), "(1,234)");
+        assert_eq!(num(1234.0, "#,##0 ;(#,##0)"), "1,234 ");
+        assert_eq!(num(0.0, "0;-0;\"zero\""), "zero");
+        assert_eq!(num(-2.0, "0;-0;\"zero\""), "-2");
+        assert_eq!(num(1234.5, "[$$-409]#,##0.00"), "$1,234.50");
+        assert_eq!(num(3.0, "[Red]0.0"), "3.0");
+        assert_eq!(num(3.0, "0_);(0)"), "3 ");
+        assert_eq!(num(0.0, "0;-0;;@"), "");
+        assert_eq!(num(0.0, "#,##0;-#,##0;"), "");
+        assert_eq!(num(-5.0, "0;-0;;@"), "-5");
+        assert_eq!(num(5.0, ""), "5");
+    }
+
+    #[test]
+    fn percent_and_scientific() {
+        assert_eq!(num(0.1234, "0.00%"), "12.34%");
+        assert_eq!(num(12345.0, "0.00E+00"), "1.23E+04");
+        assert_eq!(num(0.00012, "0.00E+00"), "1.20E-04");
+        assert_eq!(num(12345.0, "##0.0E+0"), "12.3E+3");
+        assert_eq!(num(9.999, "0.00E+00"), "1.00E+01");
+        assert_eq!(num(999999.0, "##0.0E+0"), "1.0E+6");
+        assert_eq!(num(1e12, "General"), "1E+12");
+        assert_eq!(num(f64::INFINITY, "General"), "#NUM!");
+        assert_eq!(num(f64::NAN, "0.00"), "#NUM!");
+    }
+
+    #[test]
+    fn dates_and_times() {
+        let date = ExcelValue::Date(NaiveDate::from_ymd_opt(2021, 3, 7).unwrap());
+        assert_eq!(date.format("d-mmm-yy"), "7-Mar-21");
+        assert_eq!(date.format("mm-dd-yy"), "03-07-21");
+        assert_eq!(date.format("dddd, mmmm d, yyyy"), "Sunday, March 7, 2021");
+        let datetime = ExcelValue::DateTime(NaiveDate::from_ymd_opt(2021, 3, 7).unwrap().and_hms_opt(15, 4, 5).unwrap());
+        assert_eq!(datetime.format("m/d/yy h:mm"), "3/7/21 15:04");
+        assert_eq!(datetime.format("h:mm:ss AM/PM"), "3:04:05 PM");
+        assert_eq!(num(1.5, "[h]:mm:ss"), "36:00:00");
+        assert_eq!(num(0.000_011_6, "mm:ss.0"), "00:01.0");
+        assert_eq!(num(1.0, "yyyy-mm-dd"), "1900-01-01");
+        assert_eq!(num(61.0, "yyyy-mm-dd"), "1900-03-01");
+        assert_eq!(num(2958465.0, "yyyy-mm-dd"), "9999-12-31");
+        assert_eq!(num(1e15, "yyyy-mm-dd"), "#####");
+        assert_eq!(num(-1.0, "[h]:mm"), "#####");
+    }
+
+    #[test]
+    fn numbers_in_text_formats() {
+        assert_eq!(num(5.0, "@"), "5");
+        assert_eq!(num(1234.5, "\"#\"@"), "#1234.5");
+    }
+
+    #[test]
+    fn text_and_others() {
+        let text = ExcelValue::String("abc".into());
+        assert_eq!(text.format("General"), "abc");
+        assert_eq!(text.format("0;0;0;\"<\"@\">\""), "<abc>");
+        assert_eq!(ExcelValue::Bool(true).format("General"), "TRUE");
+        assert_eq!(ExcelValue::Error(CellError::NA).format("0.00"), "#N/A");
+        assert_eq!(ExcelValue::None.format("0.00"), "");
+    }
+}
@@ -0,0 +1,207 @@
+//! This module turns a worksheet into a polars `DataFrame` (see `Worksheet::to_dataframe`). It is
+//! only available with the `polars` feature.
+
+use chrono::NaiveDate;
+use polars::prelude::*;
+use crate::error::XlError;
+use crate::utils;
+use crate::wb::Workbook;
+use crate::ws::{ExcelValue, Worksheet};
+
+/// Options for `Worksheet::to_dataframe`. By default, the type of each column is inferred from its
+/// cells; use `as_string` to keep particular columns as text (e.g., zip codes or ids that only
+/// look like numbers), or turn inference off altogether with `infer_types(false)`.
+///
+/// # Example usage
+///
+///     use xl::DataFrameOptions;
+///
+///     let opts = DataFrameOptions::new().as_string(&["Zip"]);
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataFrameOptions {
+    infer_types: bool,
+    string_columns: Vec<String>,
+}
+
+impl Default for DataFrameOptions {
+    fn default() -> Self {
+        DataFrameOptions { infer_types: true, string_columns: vec![] }
+    }
+}
+
+impl DataFrameOptions {
+    pub fn new() -> Self {
+        DataFrameOptions::default()
+    }
+
+    /// Should the type of each column be inferred from its cells? If not, every column is text.
+    pub fn infer_types(mut self, infer: bool) -> Self {
+        self.infer_types = infer;
+        self
+    }
+
+    /// Keep the columns with these names (from the header row) as text.
+    pub fn as_string(mut self, columns: &[&str]) -> Self {
+        self.string_columns.extend(columns.iter().map(|c| c.to_string()));
+        self
+    }
+}
+
+/// The type a column ends up with
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColumnType {
+    Int,
+    Float,
+    Bool,
+    Date,
+    Text,
+}
+
+/// Decide on the type of a column from its (non-blank) values. A column only gets a type other
+/// than text if *all* of its values agree.
+fn infer_type(values: &[ExcelValue]) -> ColumnType {
+    let mut kind = None;
+    for value in values {
+        let this = match value {
+            ExcelValue::None => continue,
+            ExcelValue::Number(n) if n.fract() == 0.0 && n.abs() < 9.0e15 => ColumnType::Int,
+            ExcelValue::Number(_) => ColumnType::Float,
+            ExcelValue::Bool(_) => ColumnType::Bool,
+            ExcelValue::Date(_) => ColumnType::Date,
+            _ => return ColumnType::Text,
+        };
+        kind = match (kind, this) {
+            (None, this) => Some(this),
+            (Some(ColumnType::Int), ColumnType::Float) | (Some(ColumnType::Float), ColumnType::Int) => {
+                Some(ColumnType::Float)
+            },
+            (Some(kind), this) if kind == this => Some(kind),
+            _ => return ColumnType::Text,
+        };
+    }
+    kind.unwrap_or(ColumnType::Text)
+}
+
+/// The text of a value in a text column
+fn text(value: &ExcelValue) -> Option<String> {
+    match value {
+        ExcelValue::None => None,
+        ExcelValue::String(s) => Some(s.to_string()),
+        ExcelValue::Time(t) => Some(t.to_string()),
+        value => Some(value.to_string()),
+    }
+}
+
+fn column(name: &str, values: &[ExcelValue], kind: ColumnType) -> Series {
+    match kind {
+        ColumnType::Int => {
+            let values: Vec<Option<i64>> = values.iter().map(|v| match v {
+                ExcelValue::Number(n) => Some(*n as i64),
+                _ => None,
+            }).collect();
+            Series::new(name, values)
+        },
+        ColumnType::Float => {
+            let values: Vec<Option<f64>> = values.iter().map(|v| match v {
+                ExcelValue::Number(n) => Some(*n),
+                _ => None,
+            }).collect();
+            Series::new(name, values)
+        },
+        ColumnType::Bool => {
+            let values: Vec<Option<bool>> = values.iter().map(|v| match v {
+                ExcelValue::Bool(b) => Some(*b),
+                _ => None,
+            }).collect();
+            Series::new(name, values)
+        },
+        ColumnType::Date => {
+            // polars stores dates as the number of days since 1970-01-01
+            let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+            let days: Vec<Option<i32>> = values.iter().map(|v| match v {
+                ExcelValue::Date(d) => Some((*d - epoch).num_days() as i32),
+                _ => None,
+            }).collect();
+            Int32Chunked::from_iter_options(name, days.into_iter()).into_date().into_series()
+        },
+        ColumnType::Text => {
+            let values: Vec<Option<String>> = values.iter().map(text).collect();
+            Series::new(name, values)
+        },
+    }
+}
+
+/// Build a `DataFrame` from `worksheet`, using its first row for the column names.
+pub(crate) fn to_dataframe(
+    worksheet: &Worksheet,
+    workbook: &mut Workbook,
+    opts: &DataFrameOptions,
+) -> Result<DataFrame, XlError> {
+    let mut rows = worksheet.rows(workbook)?;
+    let header = match rows.next() {
+        Some(header) => header,
+        None => return Ok(DataFrame::default()),
+    };
+    let mut names: Vec<String> = Vec::with_capacity(header.0.len());
+    for (i, cell) in header.0.iter().enumerate() {
+        let mut name = text(&cell.value).unwrap_or_default();
+        if name.is_empty() {
+            name = utils::num2col(i as u16 + 1).unwrap_or_default();
+        }
+        // polars needs every column name to be unique
+        let base = name.clone();
+        let mut n = 1;
+        while names.contains(&name) {
+            n += 1;
+            name = format!("{}_{}", base, n);
+        }
+        names.push(name);
+    }
+    let mut columns: Vec<Vec<ExcelValue>> = vec![vec![]; names.len()];
+    for row in rows {
+        for (i, column) in columns.iter_mut().enumerate() {
+            let value = row.0.get(i).map(|cell| cell.value.clone()).unwrap_or(ExcelValue::None);
+            column.push(value);
+        }
+    }
+    let series: Vec<Series> = names.iter().zip(&columns).map(|(name, values)| {
+        let kind = if !opts.infer_types || opts.string_columns.contains(name) {
+            ColumnType::Text
+        } else {
+            infer_type(values)
+        };
+        column(name, values, kind)
+    }).collect();
+    Ok(DataFrame::new(series).expect("columns have unique names and the same length"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn infer_types() {
+        use ExcelValue::*;
+        assert_eq!(infer_type(&[Number(1.0), None, Number(2.0)]), ColumnType::Int);
+        assert_eq!(infer_type(&[Number(1.0), Number(2.5)]), ColumnType::Float);
+        assert_eq!(infer_type(&[Bool(true), None]), ColumnType::Bool);
+        assert_eq!(infer_type(&[Number(1.0), String("x".into())]), ColumnType::Text);
+        assert_eq!(infer_type(&[None]), ColumnType::Text);
+    }
+
+    #[test]
+    fn dataframe() {
+        let mut wb = Workbook::open("tests/data/frame.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let df = ws.to_dataframe(&mut wb, &DataFrameOptions::new()).unwrap();
+        let types: Vec<_> = df.dtypes();
+        assert_eq!(types, vec![DataType::Utf8, DataType::Int64, DataType::Float64, DataType::Boolean, DataType::Date, DataType::Utf8]);
+        assert_eq!(df.column("Qty").unwrap().null_count(), 1);
+        assert_eq!(df.column("Zip").unwrap().utf8().unwrap().get(0), Some("2134"));
+        let df = ws.to_dataframe(&mut wb, &DataFrameOptions::new().as_string(&["Qty"])).unwrap();
+        assert_eq!(df.column("Qty").unwrap().dtype(), &DataType::Utf8);
+        let df = ws.to_dataframe(&mut wb, &DataFrameOptions::new().infer_types(false)).unwrap();
+        assert!(df.dtypes().iter().all(|t| *t == DataType::Utf8));
+    }
+}
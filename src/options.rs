@@ -0,0 +1,177 @@
+//! `ReadOptions` let you tell a `RowIter` which part of a worksheet you want (the same things you
+//! can ask `xlcat` for on the command line).
+
+use crate::error::XlError;
+use crate::utils::{self, RangeRef};
+
+/// Options for reading a worksheet with `Worksheet::rows_with`. Build one with `ReadOptions::new`
+/// and chain the options you want.
+///
+/// # Example usage
+///
+///     use xl::{ReadOptions, Workbook};
+///
+///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+///     let sheets = wb.sheets();
+///     let ws = sheets.get("Sheet1").unwrap();
+///     let opts = ReadOptions::new().range("B2:C10").max_rows(3);
+///     let rows: Vec<_> = ws.rows_with(&mut wb, &opts).unwrap().collect();
+///     assert_eq!(rows.len(), 3);
+///     assert_eq!(rows[0].1, 2);
+///     assert_eq!(rows[0].0.len(), 2);
+///     assert_eq!(rows[0][0].reference, "B2");
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReadOptions {
+    max_rows: Option<usize>,
+    columns: Option<Vec<String>>,
+    range: Option<String>,
+    trim_strings: bool,
+    collapse_whitespace: bool,
+    detect_error_text: bool,
+    treat_leap_bug_as_number: bool,
+    comma_decimal: bool,
+}
+
+/// The options in a `ReadOptions` after they have been checked and converted to numbers
+pub(crate) struct Selection {
+    pub(crate) max_rows: Option<usize>,
+    pub(crate) columns: Option<Vec<u16>>,
+    pub(crate) first_row: usize,
+    pub(crate) final_row: Option<usize>,
+    pub(crate) trim_strings: bool,
+    pub(crate) collapse_whitespace: bool,
+    pub(crate) detect_error_text: bool,
+    pub(crate) treat_leap_bug_as_number: bool,
+    pub(crate) comma_decimal: bool,
+}
+
+impl ReadOptions {
+    /// Read everything (the same as `Worksheet::rows`) until you say otherwise.
+    pub fn new() -> Self {
+        ReadOptions::default()
+    }
+
+    /// Stop after returning `n` rows.
+    pub fn max_rows(mut self, n: usize) -> Self {
+        self.max_rows = Some(n);
+        self
+    }
+
+    /// Only return the given columns (by letter, e.g., `&["A", "C"]`), in the order given. See
+    /// `RowIter::select_columns` for details.
+    pub fn columns(mut self, cols: &[&str]) -> Self {
+        self.columns = Some(cols.iter().map(|c| c.to_string()).collect());
+        self
+    }
+
+    /// Only return the cells in `range` (e.g., "A1:C10" or a single cell like "B2"). Rows before
+    /// the range are skipped, and we stop reading once we are past it. If you also pick
+    /// `columns`, those columns are used instead of the columns in the range.
+    pub fn range(mut self, range: &str) -> Self {
+        self.range = Some(range.to_owned());
+        self
+    }
+
+    /// Remove the whitespace at the start and end of text values (off by default, so you get
+    /// exactly what is in the workbook). Only `ExcelValue::String`s are trimmed; the `raw_value`
+    /// of each cell is left alone.
+    ///
+    /// Note that plain spaces, tabs, and line breaks at either end of the text are already
+    /// dropped when the xml is read (even if the cell says `xml:space="preserve"`), so this
+    /// matters most for other whitespace, such as the non-breaking spaces copied in from web
+    /// pages.
+    pub fn trim_strings(mut self, trim: bool) -> Self {
+        self.trim_strings = trim;
+        self
+    }
+
+    /// Like `trim_strings`, but also replace every run of whitespace inside text values with a
+    /// single space (e.g., "two \u{a0}  words" becomes "two words").
+    pub fn collapse_whitespace(mut self, collapse: bool) -> Self {
+        self.collapse_whitespace = collapse;
+        self
+    }
+
+    /// Read formula results that were cached as text but spell out one of Excel's errors (e.g.,
+    /// "#N/A") as `ExcelValue::Error`s. Excel itself marks errors with their own cell type, but
+    /// some programs write them as plain text, which hides them from `Cell::is_error`. This is off
+    /// by default since a formula can legitimately return text like "#N/A". Only the errors Excel
+    /// produces (see `CellError`) are recognized, and text typed into a cell is never changed.
+    pub fn detect_error_text(mut self, detect: bool) -> Self {
+        self.detect_error_text = detect;
+        self
+    }
+
+    /// Read the serial number 60 as the number 60 even when its cell is formatted as a date. Under
+    /// the 1900 date system, Excel uses 60 for February 29, 1900, a day that never happened (see
+    /// `xl::excel_number_to_date`), so by default such a cell holds an
+    /// `ExcelValue::Error(CellError::Other(...))`. Some data uses 60 as a plain number, though,
+    /// and this keeps it. Cells that are not formatted as dates are always read as numbers, so
+    /// this only changes cells with a date format.
+    pub fn treat_leap_bug_as_number(mut self, as_number: bool) -> Self {
+        self.treat_leap_bug_as_number = as_number;
+        self
+    }
+
+    /// Read numbers written with a comma as their decimal separator (e.g., "3,14") as numbers.
+    /// Excel always uses a period, but some exporters in European locales write the number the
+    /// way it is shown to their users. Without this, such a value is left as text. This is off
+    /// by default, and only a value that is not already a valid number has its comma replaced
+    /// (so "1,234,5" is still text either way).
+    pub fn comma_decimal(mut self, comma: bool) -> Self {
+        self.comma_decimal = comma;
+        self
+    }
+
+    /// Check the options and convert the column letters and range into numbers.
+    pub(crate) fn selection(&self) -> Result<Selection, XlError> {
+        let mut selection = Selection {
+            max_rows: self.max_rows,
+            columns: None,
+            first_row: 1,
+            final_row: None,
+            trim_strings: self.trim_strings || self.collapse_whitespace,
+            collapse_whitespace: self.collapse_whitespace,
+            detect_error_text: self.detect_error_text,
+            treat_leap_bug_as_number: self.treat_leap_bug_as_number,
+            comma_decimal: self.comma_decimal,
+        };
+        if let Some(range) = &self.range {
+            let range: RangeRef = range.parse()?;
+            selection.columns = Some((range.first_col..=range.last_col).collect());
+            selection.first_row = range.first_row as usize;
+            selection.final_row = Some(range.last_row as usize);
+        }
+        if let Some(cols) = &self.columns {
+            let mut nums = Vec::with_capacity(cols.len());
+            for col in cols {
+                match utils::col2num(&col.to_uppercase()) {
+                    Some(num) => nums.push(num),
+                    None => return Err(XlError::BadOption(format!("invalid column: {}", col))),
+                }
+            }
+            selection.columns = Some(nums);
+        }
+        Ok(selection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_options() {
+        assert!(ReadOptions::new().range("C3:A1").selection().is_err());
+        assert!(ReadOptions::new().range("nonsense").selection().is_err());
+        assert!(ReadOptions::new().columns(&["A", "1"]).selection().is_err());
+    }
+
+    #[test]
+    fn columns_win_over_range() {
+        let selection = ReadOptions::new().range("B2:D4").columns(&["a", "C"]).selection().unwrap();
+        assert_eq!(selection.columns, Some(vec![1, 3]));
+        assert_eq!(selection.first_row, 2);
+        assert_eq!(selection.final_row, Some(4));
+    }
+}
@@ -0,0 +1,712 @@
+//! This module lets you take apart Excel formulas (e.g., to figure out which cells a formula
+//! depends on). It has two layers: a `Lexer` that breaks a formula into `Token`s, and a
+//! recursive-descent parser on top of it that turns those tokens into an `Expr` tree. Most people
+//! will only want `parse_formula`.
+
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+use crate::utils::{self, CellRef};
+use crate::ws::CellError;
+
+/// The pieces a formula is made of. These are what the `Lexer` yields.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    /// A numeric literal ("1", "2.5", "1E3"); a leading '-' is a separate `Minus` token
+    Number(f64),
+    /// A string literal (without its quotes, and with doubled quotes collapsed)
+    Str(String),
+    /// TRUE or FALSE (in any case)
+    Bool(bool),
+    /// An error literal like "#N/A"
+    Error(CellError),
+    /// A cell reference ("A1", "$B$2") or an area ("A1:B10", "A:C", "1:3")
+    Range(String),
+    /// A function name, defined name, or sheet name (quoted sheet names lose their quotes)
+    Ident(String),
+    /// The '!' between a sheet name and a reference
+    Bang,
+    /// '+'
+    Plus,
+    /// '-'
+    Minus,
+    /// '*'
+    Star,
+    /// '/'
+    Slash,
+    /// '^'
+    Caret,
+    /// '&' (string concatenation)
+    Amp,
+    /// '%'
+    Percent,
+    /// '='
+    Eq,
+    /// '<>'
+    Ne,
+    /// '<'
+    Lt,
+    /// '<='
+    Le,
+    /// '>'
+    Gt,
+    /// '>='
+    Ge,
+    /// A ':' that is not part of a `Range` (e.g., between two names)
+    Colon,
+    /// ',' (separates function arguments and array columns)
+    Comma,
+    /// ';' (separates array rows)
+    Semicolon,
+    /// '('
+    LParen,
+    /// ')'
+    RParen,
+    /// '{' (starts an array constant)
+    LBrace,
+    /// '}'
+    RBrace,
+}
+
+/// Something went wrong while lexing or parsing a formula.
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    /// A character that cannot appear in a formula (at the given byte offset)
+    InvalidCharacter(char, usize),
+    /// A string literal or quoted sheet name that is never closed
+    UnterminatedString,
+    /// Something that starts like a number but is not one (e.g. "1.2.3")
+    InvalidNumber(String),
+    /// A token we did not expect at this point in the formula
+    UnexpectedToken(Token),
+    /// The formula ended before we were done with it
+    UnexpectedEnd,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::InvalidCharacter(c, pos) => write!(f, "invalid character '{}' at position {}", c, pos),
+            ParseError::UnterminatedString => write!(f, "unterminated string"),
+            ParseError::InvalidNumber(n) => write!(f, "invalid number: {}", n),
+            ParseError::UnexpectedToken(t) => write!(f, "unexpected token: {:?}", t),
+            ParseError::UnexpectedEnd => write!(f, "unexpected end of formula"),
+        }
+    }
+}
+
+/// The `Lexer` is an iterator over the `Token`s in a formula. Whitespace is skipped and a leading
+/// '=' should be removed before the formula is handed to the lexer. If the lexer runs into
+/// something it cannot make sense of, it yields a `ParseError` (you can keep going after that,
+/// but the tokens that follow are not likely to be useful).
+///
+/// # Example usage
+///
+///     use xl::{Lexer, Token};
+///
+///     let tokens: Result<Vec<Token>, _> = Lexer::new("SUM(A1:B2)*2").collect();
+///     assert_eq!(tokens.unwrap(), vec![
+///         Token::Ident("SUM".to_owned()),
+///         Token::LParen,
+///         Token::Range("A1:B2".to_owned()),
+///         Token::RParen,
+///         Token::Star,
+///         Token::Number(2.0),
+///     ]);
+pub struct Lexer<'a> {
+    formula: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(formula: &'a str) -> Self {
+        Lexer { formula, chars: formula.char_indices().peekable() }
+    }
+
+    /// Consume characters while `pred` holds and return the slice of the formula they cover
+    fn take_while<F: Fn(char) -> bool>(&mut self, start: usize, pred: F) -> &'a str {
+        let mut end = start;
+        while let Some(&(i, c)) = self.chars.peek() {
+            if !pred(c) { break }
+            end = i + c.len_utf8();
+            self.chars.next();
+        }
+        &self.formula[start..end]
+    }
+
+    /// Read a quoted string whose opening `quote` was already consumed. A doubled quote stands
+    /// for the quote character itself.
+    fn quoted(&mut self, quote: char) -> Result<String, ParseError> {
+        let mut s = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, c)) if c == quote => {
+                    if let Some(&(_, next)) = self.chars.peek() {
+                        if next == quote {
+                            s.push(quote);
+                            self.chars.next();
+                            continue
+                        }
+                    }
+                    return Ok(s)
+                },
+                Some((_, c)) => s.push(c),
+                None => return Err(ParseError::UnterminatedString),
+            }
+        }
+    }
+
+    fn number(&mut self, start: usize) -> Result<Token, ParseError> {
+        let mut end = self.take_while(start, |c| c.is_ascii_digit() || c == '.').len() + start;
+        // exponent (e.g. 1.5E+10)
+        let rest = &self.formula[end..];
+        if rest.starts_with(['e', 'E']) {
+            let sign = rest[1..].starts_with(['+', '-']) as usize;
+            if rest[1 + sign..].starts_with(|c: char| c.is_ascii_digit()) {
+                for _ in 0..1 + sign { self.chars.next(); }
+                end += 1 + sign;
+                end += self.take_while(end, |c| c.is_ascii_digit()).len();
+            }
+        }
+        let text = &self.formula[start..end];
+        // a row range like 1:3
+        if !text.contains('.') && self.formula[end..].starts_with(':') {
+            let digits = self.formula[end + 1..].chars().take_while(|c| c.is_ascii_digit()).count();
+            if digits > 0 {
+                self.chars.next();
+                self.take_while(end + 1, |c| c.is_ascii_digit());
+                return Ok(Token::Range(self.formula[start..end + 1 + digits].to_owned()))
+            }
+        }
+        text.parse().map(Token::Number).map_err(|_| ParseError::InvalidNumber(text.to_owned()))
+    }
+
+    fn word(&mut self, start: usize) -> Token {
+        let word = self.take_while(start, |c| c.is_alphanumeric() || c == '_' || c == '.' || c == '$' || c == '\\');
+        let end = start + word.len();
+        if self.formula[end..].starts_with('!') {
+            // a sheet name (even one that looks like a reference, e.g. Q1!A1)
+            return Token::Ident(word.to_owned())
+        }
+        let is_column = |w: &str| {
+            let w = w.strip_prefix('$').unwrap_or(w);
+            !w.is_empty() && utils::col2num(w).is_some()
+        };
+        if utils::parse_cell_reference(word).is_some() || is_column(word) {
+            // look for the other end of an area (A1:B2 or A:C)
+            if self.formula[end..].starts_with(':') {
+                let other: String = self.formula[end + 1..]
+                    .chars()
+                    .take_while(|&c| c.is_ascii_alphanumeric() || c == '$')
+                    .collect();
+                let same_kind = if is_column(word) {
+                    is_column(&other)
+                } else {
+                    utils::parse_cell_reference(&other).is_some()
+                };
+                if same_kind {
+                    self.chars.next();
+                    self.take_while(end + 1, |c| c.is_ascii_alphanumeric() || c == '$');
+                    return Token::Range(self.formula[start..end + 1 + other.len()].to_owned())
+                }
+            }
+            if utils::parse_cell_reference(word).is_some() && !self.formula[end..].starts_with('(') {
+                return Token::Range(word.to_owned())
+            }
+        }
+        if !self.formula[end..].starts_with('(') {
+            if word.eq_ignore_ascii_case("TRUE") { return Token::Bool(true) }
+            if word.eq_ignore_ascii_case("FALSE") { return Token::Bool(false) }
+        }
+        Token::Ident(word.to_owned())
+    }
+
+    fn error(&mut self, start: usize) -> Result<Token, ParseError> {
+        let rest = self.formula[start..].to_uppercase();
+        for e in CellError::CANONICAL.iter() {
+            if rest.starts_with(e.as_str()) {
+                for _ in 0..e.as_str().chars().count() { self.chars.next(); }
+                return Ok(Token::Error(e.clone()))
+            }
+        }
+        Err(ParseError::InvalidCharacter('#', start))
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Result<Token, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(&(_, c)) = self.chars.peek() {
+            if !c.is_whitespace() { break }
+            self.chars.next();
+        }
+        let &(start, c) = self.chars.peek()?;
+        let single = |t: Token| Some(Ok(t));
+        if c.is_ascii_digit() || (c == '.' && self.formula[start + 1..].starts_with(|c: char| c.is_ascii_digit())) {
+            return Some(self.number(start))
+        }
+        if c.is_alphabetic() || c == '_' || c == '$' || c == '\\' {
+            return Some(Ok(self.word(start)))
+        }
+        if c == '#' {
+            return Some(self.error(start))
+        }
+        self.chars.next();
+        match c {
+            '"' => Some(self.quoted('"').map(Token::Str)),
+            '\'' => Some(self.quoted('\'').map(Token::Ident)),
+            '+' => single(Token::Plus),
+            '-' => single(Token::Minus),
+            '*' => single(Token::Star),
+            '/' => single(Token::Slash),
+            '^' => single(Token::Caret),
+            '&' => single(Token::Amp),
+            '%' => single(Token::Percent),
+            '=' => single(Token::Eq),
+            '!' => single(Token::Bang),
+            ':' => single(Token::Colon),
+            ',' => single(Token::Comma),
+            ';' => single(Token::Semicolon),
+            '(' => single(Token::LParen),
+            ')' => single(Token::RParen),
+            '{' => single(Token::LBrace),
+            '}' => single(Token::RBrace),
+            '<' => match self.chars.peek() {
+                Some(&(_, '=')) => { self.chars.next(); single(Token::Le) },
+                Some(&(_, '>')) => { self.chars.next(); single(Token::Ne) },
+                _ => single(Token::Lt),
+            },
+            '>' => match self.chars.peek() {
+                Some(&(_, '=')) => { self.chars.next(); single(Token::Ge) },
+                _ => single(Token::Gt),
+            },
+            _ => Some(Err(ParseError::InvalidCharacter(c, start))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Plus,
+    Minus,
+    /// The postfix '%' (divides by 100)
+    Percent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Concat,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// The ':' operator when it joins something other than two plain references (e.g.
+    /// `A1:INDEX(B:B, 5)`)
+    Range,
+}
+
+/// A parsed formula.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Str(String),
+    Bool(bool),
+    Error(CellError),
+    /// A cell reference or area, possibly on another sheet (e.g. `Sheet2!A1:B3`)
+    Reference { sheet: Option<String>, reference: String },
+    /// A defined name (e.g. `TaxRate`), possibly scoped to a sheet
+    Name { sheet: Option<String>, name: String },
+    Function { name: String, args: Vec<Expr> },
+    Unary { op: UnaryOp, expr: Box<Expr> },
+    Binary { op: BinaryOp, left: Box<Expr>, right: Box<Expr> },
+    /// An array constant like `{1,2;3,4}` (a list of rows)
+    Array(Vec<Vec<Expr>>),
+    /// A function argument that was left out (e.g. the second argument of `IF(A1,,1)`)
+    Missing,
+}
+
+struct Parser<'a> {
+    lexer: Peekable<Lexer<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&mut self) -> Result<Option<&Token>, ParseError> {
+        if let Some(Err(_)) = self.lexer.peek() {
+            return Err(self.lexer.next().unwrap().unwrap_err())
+        }
+        match self.lexer.peek() {
+            Some(Ok(t)) => Ok(Some(t)),
+            _ => Ok(None),
+        }
+    }
+
+    fn next(&mut self) -> Result<Token, ParseError> {
+        match self.lexer.next() {
+            Some(t) => t,
+            None => Err(ParseError::UnexpectedEnd),
+        }
+    }
+
+    fn expect(&mut self, want: Token) -> Result<(), ParseError> {
+        match self.next()? {
+            t if t == want => Ok(()),
+            t => Err(ParseError::UnexpectedToken(t)),
+        }
+    }
+
+    /// Parse a chain of left-associative binary operators. `op` maps the next token to an operator
+    /// (if it is one at this level) and `operand` parses the next level down.
+    fn binary<O, F>(&mut self, op: O, operand: F) -> Result<Expr, ParseError>
+    where
+        O: Fn(&Token) -> Option<BinaryOp>,
+        F: Fn(&mut Self) -> Result<Expr, ParseError>,
+    {
+        let mut left = operand(self)?;
+        while let Some(op) = self.peek()?.and_then(&op) {
+            self.next()?;
+            let right = operand(self)?;
+            left = Expr::Binary { op, left: Box::new(left), right: Box::new(right) };
+        }
+        Ok(left)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        self.binary(|t| match t {
+            Token::Eq => Some(BinaryOp::Eq),
+            Token::Ne => Some(BinaryOp::Ne),
+            Token::Lt => Some(BinaryOp::Lt),
+            Token::Le => Some(BinaryOp::Le),
+            Token::Gt => Some(BinaryOp::Gt),
+            Token::Ge => Some(BinaryOp::Ge),
+            _ => None,
+        }, Self::concat)
+    }
+
+    fn concat(&mut self) -> Result<Expr, ParseError> {
+        self.binary(|t| match t {
+            Token::Amp => Some(BinaryOp::Concat),
+            _ => None,
+        }, Self::additive)
+    }
+
+    fn additive(&mut self) -> Result<Expr, ParseError> {
+        self.binary(|t| match t {
+            Token::Plus => Some(BinaryOp::Add),
+            Token::Minus => Some(BinaryOp::Sub),
+            _ => None,
+        }, Self::multiplicative)
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, ParseError> {
+        self.binary(|t| match t {
+            Token::Star => Some(BinaryOp::Mul),
+            Token::Slash => Some(BinaryOp::Div),
+            _ => None,
+        }, Self::power)
+    }
+
+    fn power(&mut self) -> Result<Expr, ParseError> {
+        // note: Excel evaluates 2^3^2 as (2^3)^2, so this is left-associative like the rest
+        self.binary(|t| match t {
+            Token::Caret => Some(BinaryOp::Pow),
+            _ => None,
+        }, Self::percent)
+    }
+
+    fn percent(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.unary()?;
+        while let Some(Token::Percent) = self.peek()? {
+            self.next()?;
+            expr = Expr::Unary { op: UnaryOp::Percent, expr: Box::new(expr) };
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        // note: negation binds more tightly than '^' in Excel, so -2^2 is 4
+        let op = match self.peek()? {
+            Some(Token::Minus) => UnaryOp::Minus,
+            Some(Token::Plus) => UnaryOp::Plus,
+            _ => return self.range(),
+        };
+        self.next()?;
+        Ok(Expr::Unary { op, expr: Box::new(self.unary()?) })
+    }
+
+    fn range(&mut self) -> Result<Expr, ParseError> {
+        self.binary(|t| match t {
+            Token::Colon => Some(BinaryOp::Range),
+            _ => None,
+        }, Self::primary)
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        match self.next()? {
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::Str(s) => Ok(Expr::Str(s)),
+            Token::Bool(b) => Ok(Expr::Bool(b)),
+            Token::Error(e) => Ok(Expr::Error(e)),
+            Token::Range(r) => Ok(Expr::Reference { sheet: None, reference: r }),
+            Token::LParen => {
+                let expr = self.comparison()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            },
+            Token::LBrace => self.array(),
+            Token::Ident(name) => match self.peek()? {
+                Some(Token::LParen) => {
+                    self.next()?;
+                    Ok(Expr::Function { name: name.to_uppercase(), args: self.arguments()? })
+                },
+                Some(Token::Bang) => {
+                    self.next()?;
+                    match self.next()? {
+                        Token::Range(r) => Ok(Expr::Reference { sheet: Some(name), reference: r }),
+                        Token::Ident(n) => Ok(Expr::Name { sheet: Some(name), name: n }),
+                        Token::Error(e) => Ok(Expr::Error(e)),
+                        t => Err(ParseError::UnexpectedToken(t)),
+                    }
+                },
+                _ => Ok(Expr::Name { sheet: None, name }),
+            },
+            t => Err(ParseError::UnexpectedToken(t)),
+        }
+    }
+
+    /// Parse function arguments (the opening parenthesis was already consumed)
+    fn arguments(&mut self) -> Result<Vec<Expr>, ParseError> {
+        let mut args = Vec::new();
+        if let Some(Token::RParen) = self.peek()? {
+            self.next()?;
+            return Ok(args)
+        }
+        loop {
+            match self.peek()? {
+                Some(Token::Comma) | Some(Token::RParen) => args.push(Expr::Missing),
+                _ => args.push(self.comparison()?),
+            }
+            match self.next()? {
+                Token::Comma => continue,
+                Token::RParen => return Ok(args),
+                t => return Err(ParseError::UnexpectedToken(t)),
+            }
+        }
+    }
+
+    /// Parse an array constant (the opening brace was already consumed)
+    fn array(&mut self) -> Result<Expr, ParseError> {
+        let mut rows = vec![Vec::new()];
+        loop {
+            rows.last_mut().unwrap().push(self.unary()?);
+            match self.next()? {
+                Token::Comma => (),
+                Token::Semicolon => rows.push(Vec::new()),
+                Token::RBrace => return Ok(Expr::Array(rows)),
+                t => return Err(ParseError::UnexpectedToken(t)),
+            }
+        }
+    }
+}
+
+/// Parse `formula` (with or without its leading '=') into an `Expr`.
+///
+/// # Example usage
+///
+///     use xl::{parse_formula, BinaryOp, Expr};
+///
+///     let expr = parse_formula("=A1+B1").unwrap();
+///     let a1 = Expr::Reference { sheet: None, reference: "A1".to_owned() };
+///     let b1 = Expr::Reference { sheet: None, reference: "B1".to_owned() };
+///     assert_eq!(expr, Expr::Binary { op: BinaryOp::Add, left: Box::new(a1), right: Box::new(b1) });
+pub fn parse_formula(formula: &str) -> Result<Expr, ParseError> {
+    let formula = formula.strip_prefix('=').unwrap_or(formula);
+    let mut parser = Parser { lexer: Lexer::new(formula).peekable() };
+    let expr = parser.comparison()?;
+    match parser.next() {
+        Err(ParseError::UnexpectedEnd) => Ok(expr),
+        Ok(t) => Err(ParseError::UnexpectedToken(t)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Return every cell `formula` refers to, in the order they appear. A range is represented by the
+/// two cells at its corners (top-left, then bottom-right), so "A1:B3" gives you A1 and B3. Whole
+/// columns (e.g., "A:B") run from row 1 to the last row of a worksheet and whole rows (e.g., "1:3")
+/// from column A to the last column. References to other sheets keep the name of the sheet in
+/// `CellRef::sheet`.
+///
+/// This only looks at the tokens in the formula, so it still works on formulas `parse_formula`
+/// cannot handle (anything after a token the `Lexer` does not understand is ignored, though).
+///
+/// # Example usage
+///
+///     use xl::{formula_dependencies, CellRef};
+///
+///     let deps = formula_dependencies("=SUM(A1:B3) + Sheet2!C4");
+///     assert_eq!(deps.len(), 3);
+///     assert_eq!(deps[1], CellRef::new(2, 3));
+///     assert_eq!(deps[2].to_string(), "Sheet2!C4");
+pub fn formula_dependencies(formula: &str) -> Vec<CellRef> {
+    let formula = formula.strip_prefix('=').unwrap_or(formula);
+    let mut deps = Vec::new();
+    let mut tokens = Lexer::new(formula).map_while(Result::ok).peekable();
+    while let Some(token) = tokens.next() {
+        let (sheet, range) = match token {
+            Token::Ident(sheet) if tokens.peek() == Some(&Token::Bang) => {
+                tokens.next();
+                match tokens.next() {
+                    Some(Token::Range(range)) => (Some(sheet), range),
+                    _ => continue,
+                }
+            },
+            Token::Range(range) => (None, range),
+            _ => continue,
+        };
+        for (col, row) in range_corners(&range) {
+            deps.push(CellRef { sheet: sheet.clone(), col, row });
+        }
+    }
+    deps
+}
+
+/// Return the (col, row) of each corner of a `Token::Range` ("A1" has one corner, "A1:B2" two)
+fn range_corners(range: &str) -> Vec<(u16, u32)> {
+    let corner = |part: &str, is_start: bool| {
+        if let Some((_, col, _, row)) = utils::parse_cell_reference(part) {
+            return Some((col, row))
+        }
+        let part = part.strip_prefix('$').unwrap_or(part);
+        if let Some(col) = utils::col2num(part) {
+            return Some((col, if is_start { 1 } else { utils::XL_MAX_ROW }))
+        }
+        let row = part.parse().ok()?;
+        Some((if is_start { 1 } else { utils::XL_MAX_COL }, row))
+    };
+    match range.split_once(':') {
+        Some((start, end)) => corner(start, true).into_iter().chain(corner(end, false)).collect(),
+        None => corner(range, true).into_iter().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference(sheet: Option<&str>, reference: &str) -> Expr {
+        Expr::Reference { sheet: sheet.map(|s| s.to_owned()), reference: reference.to_owned() }
+    }
+
+    fn binary(op: BinaryOp, left: Expr, right: Expr) -> Expr {
+        Expr::Binary { op, left: Box::new(left), right: Box::new(right) }
+    }
+
+    #[test]
+    fn lex_tokens() {
+        let tokens: Result<Vec<_>, _> = Lexer::new("SUM($A$1:B2, 'My Sheet'!C3) <> #N/A").collect();
+        assert_eq!(tokens.unwrap(), vec![
+            Token::Ident("SUM".to_owned()),
+            Token::LParen,
+            Token::Range("$A$1:B2".to_owned()),
+            Token::Comma,
+            Token::Ident("My Sheet".to_owned()),
+            Token::Bang,
+            Token::Range("C3".to_owned()),
+            Token::RParen,
+            Token::Ne,
+            Token::Error(CellError::NA),
+        ]);
+    }
+
+    #[test]
+    fn lex_errors() {
+        for error in CellError::CANONICAL.iter() {
+            let formula = format!("IF(A1={},0)", error.as_str().to_lowercase());
+            let tokens: Vec<_> = Lexer::new(&formula).collect::<Result<_, _>>().unwrap();
+            assert_eq!(tokens[4], Token::Error(error.clone()));
+            assert_eq!(tokens[5], Token::Comma);
+        }
+        assert_eq!(Lexer::new("#DIV/0").next(), Some(Err(ParseError::InvalidCharacter('#', 0))));
+    }
+
+    #[test]
+    fn lex_numbers_and_strings() {
+        let tokens: Result<Vec<_>, _> = Lexer::new("1.5E+3 & \"say \"\"hi\"\"\" & Q1!A:B").collect();
+        assert_eq!(tokens.unwrap(), vec![
+            Token::Number(1500.0),
+            Token::Amp,
+            Token::Str("say \"hi\"".to_owned()),
+            Token::Amp,
+            Token::Ident("Q1".to_owned()),
+            Token::Bang,
+            Token::Range("A:B".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn precedence() {
+        let expr = parse_formula("=A1+B1*2").unwrap();
+        let product = binary(BinaryOp::Mul, reference(None, "B1"), Expr::Number(2.0));
+        assert_eq!(expr, binary(BinaryOp::Add, reference(None, "A1"), product));
+    }
+
+    #[test]
+    fn negation_before_power() {
+        let expr = parse_formula("-2^2").unwrap();
+        let negated = Expr::Unary { op: UnaryOp::Minus, expr: Box::new(Expr::Number(2.0)) };
+        assert_eq!(expr, binary(BinaryOp::Pow, negated, Expr::Number(2.0)));
+    }
+
+    #[test]
+    fn functions() {
+        let expr = parse_formula("IF(Sheet2!A1:A3>0,,TRUE)").unwrap();
+        let condition = binary(BinaryOp::Gt, reference(Some("Sheet2"), "A1:A3"), Expr::Number(0.0));
+        assert_eq!(expr, Expr::Function {
+            name: "IF".to_owned(),
+            args: vec![condition, Expr::Missing, Expr::Bool(true)],
+        });
+    }
+
+    #[test]
+    fn arrays_and_names() {
+        let expr = parse_formula("{1,2;3,4}*TaxRate").unwrap();
+        let array = Expr::Array(vec![
+            vec![Expr::Number(1.0), Expr::Number(2.0)],
+            vec![Expr::Number(3.0), Expr::Number(4.0)],
+        ]);
+        let name = Expr::Name { sheet: None, name: "TaxRate".to_owned() };
+        assert_eq!(expr, binary(BinaryOp::Mul, array, name));
+    }
+
+    #[test]
+    fn dependencies() {
+        let deps = formula_dependencies("=IF('My Sheet'!$A$1>0, SUM(B2:C3), D:D) & 1:2");
+        let on_sheet = CellRef { sheet: Some("My Sheet".to_owned()), col: 1, row: 1 };
+        assert_eq!(deps, vec![
+            on_sheet,
+            CellRef::new(2, 2),
+            CellRef::new(3, 3),
+            CellRef::new(4, 1),
+            CellRef::new(4, utils::XL_MAX_ROW),
+            CellRef::new(1, 1),
+            CellRef::new(utils::XL_MAX_COL, 2),
+        ]);
+        assert_eq!(deps[0].to_string(), "'My Sheet'!A1");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse_formula("SUM(A1"), Err(ParseError::UnexpectedEnd));
+        assert_eq!(parse_formula("A1 B1"), Err(ParseError::UnexpectedToken(Token::Range("B1".to_owned()))));
+        assert_eq!(parse_formula("\"abc"), Err(ParseError::UnterminatedString));
+        assert_eq!(parse_formula("A1 ~ 2"), Err(ParseError::InvalidCharacter('~', 3)));
+        assert_eq!(parse_formula("1.2.3"), Err(ParseError::InvalidNumber("1.2.3".to_owned())));
+    }
+}
@@ -0,0 +1,75 @@
+//! This module reads the document properties of a workbook: the core properties (author, dates,
+//! title, ...) in "docProps/core.xml" and the application properties (which program wrote the file,
+//! company, ...) in "docProps/app.xml".
+
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use crate::utils::{self, TextDecoder};
+
+/// The document properties of a workbook (see `Workbook::properties`). Every property is optional,
+/// since files are not required to record any of them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocProperties {
+    /// Who created the workbook (`dc:creator`)
+    pub creator: Option<String>,
+    /// When the workbook was created, as written in the file (e.g., "2021-03-07T15:04:05Z")
+    pub created: Option<String>,
+    /// When the workbook was last saved, as written in the file
+    pub modified: Option<String>,
+    /// Who saved the workbook last (`cp:lastModifiedBy`)
+    pub last_modified_by: Option<String>,
+    /// The title of the workbook (`dc:title`)
+    pub title: Option<String>,
+    /// The program that wrote the workbook (e.g., "Microsoft Excel")
+    pub application: Option<String>,
+    /// The company the workbook belongs to
+    pub company: Option<String>,
+}
+
+impl DocProperties {
+    /// Fill in the properties found in `xml` (the contents of either "docProps/core.xml" or
+    /// "docProps/app.xml").
+    pub(crate) fn read(&mut self, xml: &[u8]) {
+        let mut reader = Reader::from_reader(utils::skip_bom(xml));
+        reader.trim_text(true);
+        let mut decoder = TextDecoder::utf8();
+        let mut buf = Vec::new();
+        let mut depth = 0;
+        let mut current: Option<Vec<u8>> = None;
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Decl(ref e)) => decoder.declared(e),
+                Ok(Event::Start(ref e)) => {
+                    depth += 1;
+                    // the properties are the children of the root element
+                    current = if depth == 2 { Some(e.local_name().to_vec()) } else { None };
+                },
+                Ok(Event::Text(ref e)) => {
+                    if let Some(name) = current.as_deref() {
+                        let text = decoder.unescape(e.escaped());
+                        let property = match name {
+                            b"creator" => Some(&mut self.creator),
+                            b"created" => Some(&mut self.created),
+                            b"modified" => Some(&mut self.modified),
+                            b"lastModifiedBy" => Some(&mut self.last_modified_by),
+                            b"title" => Some(&mut self.title),
+                            b"Application" => Some(&mut self.application),
+                            b"Company" => Some(&mut self.company),
+                            _ => None,
+                        };
+                        if let Some(property) = property {
+                            *property = Some(text);
+                        }
+                    }
+                },
+                Ok(Event::End(_)) => {
+                    depth -= 1;
+                    current = None;
+                },
+                Ok(Event::Eof) | Err(_) => break,
+                _ => (),
+            }
+            buf.clear();
+        }
+    }
+}
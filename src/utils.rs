@@ -1,10 +1,18 @@
+use std::borrow::Cow;
 use std::convert::TryInto;
-use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use std::fmt;
+use std::io::BufRead;
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use quick_xml::Reader;
+use quick_xml::escape;
+use quick_xml::events::{BytesDecl, Event};
 use quick_xml::events::attributes::{Attribute, Attributes};
+use crate::error::XlError;
 use crate::wb::DateSystem;
 
-const XL_MAX_COL: u16 = 16384;
+pub(crate) const XL_MAX_COL: u16 = 16384;
 const XL_MIN_COL: u16 = 1;
+pub(crate) const XL_MAX_ROW: u32 = 1048576;
 
 /// Return column letter for column number `n`
 pub fn num2col(n: u16) -> Option<String> {
@@ -22,17 +30,302 @@ pub fn num2col(n: u16) -> Option<String> {
 /// Return column number for column letter `letter`
 pub fn col2num(letter: &str) -> Option<u16> {
     let letter = letter.to_uppercase();
-    let mut num: u16 = 0;
+    let mut num: u32 = 0;
     for c in letter.chars() {
-        if !('A'..='Z').contains(&c) { return None }
-        num = num * 26 + ((c as u16) - ('A' as u16)) + 1;
+        if !c.is_ascii_uppercase() { return None }
+        num = num * 26 + ((c as u32) - ('A' as u32)) + 1;
+        if num > XL_MAX_COL as u32 { return None }
     }
+    let num = num as u16;
     if !(XL_MIN_COL..=XL_MAX_COL).contains(&num) { return None }
     Some(num)
 }
 
+/// Split a cell reference like "B3" into its column letters ("B") and its row number (3). Returns
+/// `None` unless `reference` is one or more letters followed by a row number Excel allows (1 to
+/// 1048576), so "B", "3", "B3C", and "B0" are all rejected. The letters are not checked against
+/// the last column Excel allows (`col2num` does that).
+pub fn split_reference(reference: &str) -> Option<(&str, u32)> {
+    let letters = reference.bytes().take_while(u8::is_ascii_alphabetic).count();
+    let (col, row) = reference.split_at(letters);
+    if col.is_empty() || row.is_empty() || !row.bytes().all(|b| b.is_ascii_digit()) {
+        return None
+    }
+    let row = row.parse().ok().filter(|row| (1..=XL_MAX_ROW).contains(row))?;
+    Some((col, row))
+}
+
+/// A reference to a single cell (e.g., "B3" or "Sheet2!B3").
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CellRef {
+    /// The sheet the cell is on, if the reference names one
+    pub sheet: Option<String>,
+    /// The (1-based) column number of the cell
+    pub col: u16,
+    /// The (1-based) row number of the cell
+    pub row: u32,
+}
+
+impl CellRef {
+    pub fn new(col: u16, row: u32) -> Self {
+        CellRef { sheet: None, col, row }
+    }
+}
+
+impl fmt::Display for CellRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(sheet) = &self.sheet {
+            if sheet.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.') {
+                write!(f, "{}!", sheet)?;
+            } else {
+                write!(f, "'{}'!", sheet.replace('\'', "''"))?;
+            }
+        }
+        write!(f, "{}{}", num2col(self.col).unwrap_or_default(), self.row)
+    }
+}
+
+/// A rectangle of cells (e.g., "B2:D10"), given by its first and last column and row (all
+/// 1-based and inclusive). Parse one from a string with `parse`; a single cell like "B2" is a
+/// range of one cell, and "$" signs are allowed (and ignored).
+///
+/// # Example usage
+///
+///     use xl::RangeRef;
+///
+///     let range: RangeRef = "B2:D10".parse().unwrap();
+///     assert_eq!((range.first_col, range.first_row), (2, 2));
+///     assert_eq!((range.last_col, range.last_row), (4, 10));
+///     assert_eq!(range.width(), 3);
+///     assert_eq!(range.height(), 9);
+///     assert_eq!(range.to_string(), "B2:D10");
+///     assert!("D10:B2".parse::<RangeRef>().is_err());
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RangeRef {
+    pub first_col: u16,
+    pub first_row: u32,
+    pub last_col: u16,
+    pub last_row: u32,
+}
+
+impl RangeRef {
+    /// The number of columns in the range
+    pub fn width(&self) -> usize {
+        (self.last_col - self.first_col) as usize + 1
+    }
+
+    /// The number of rows in the range
+    pub fn height(&self) -> usize {
+        (self.last_row - self.first_row) as usize + 1
+    }
+}
+
+impl std::str::FromStr for RangeRef {
+    type Err = XlError;
+
+    /// Parse a range like "A1:C10" (or a single cell like "B2"). The first cell has to be above
+    /// and to the left of (or the same as) the last one, otherwise you get an
+    /// `XlError::BadOption`.
+    fn from_str(range: &str) -> Result<Self, Self::Err> {
+        let bad_range = || XlError::BadOption(format!("invalid range: {}", range));
+        let (start, end) = range.split_once(':').unwrap_or((range, range));
+        let (_, first_col, _, first_row) = parse_cell_reference(start).ok_or_else(bad_range)?;
+        let (_, last_col, _, last_row) = parse_cell_reference(end).ok_or_else(bad_range)?;
+        if first_col > last_col || first_row > last_row {
+            return Err(bad_range())
+        }
+        Ok(RangeRef { first_col, first_row, last_col, last_row })
+    }
+}
+
+impl fmt::Display for RangeRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", CellRef::new(self.first_col, self.first_row))?;
+        if self.width() > 1 || self.height() > 1 {
+            write!(f, ":{}", CellRef::new(self.last_col, self.last_row))?;
+        }
+        Ok(())
+    }
+}
+
+/// Move every relative cell reference in `formula` down by `rows` and right by `cols`. This is how
+/// Excel turns the "master" copy of a shared formula into the formula of each cell that shares it
+/// (e.g., "A1*2" in B1 becomes "A2*2" in B2). Absolute references (e.g., "$A$1") are left alone,
+/// as is anything in a string literal or a quoted sheet name. References that would move off the
+/// worksheet become "#REF!". Whole-column and whole-row references (e.g., "A:A") are not adjusted.
+pub fn shift_formula(formula: &str, rows: i64, cols: i64) -> String {
+    let mut shifted = String::with_capacity(formula.len());
+    let mut chars = formula.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '"' || c == '\'' {
+            // copy string literals and quoted sheet names as-is (a doubled quote inside either
+            // just closes and reopens the quote, so it works out the same)
+            shifted.push(c);
+            for inner in chars.by_ref() {
+                shifted.push(inner);
+                if inner == c { break }
+            }
+        } else if c.is_ascii_alphanumeric() || c == '$' || c == '_' || c == '.' {
+            let mut token = c.to_string();
+            while let Some(&next) = chars.peek() {
+                if next.is_ascii_alphanumeric() || next == '$' || next == '_' || next == '.' {
+                    token.push(next);
+                    chars.next();
+                } else {
+                    break
+                }
+            }
+            // a "reference" right before a '(' or '!' is actually a function or sheet name
+            let is_name = matches!(chars.peek(), Some('(') | Some('!'));
+            match shift_reference(&token, rows, cols) {
+                Some(reference) if !is_name => shifted.push_str(&reference),
+                _ => shifted.push_str(&token),
+            }
+        } else {
+            shifted.push(c);
+        }
+    }
+    shifted
+}
+
+/// Shift a single cell reference like "A1" or "$B2". Returns `None` if `token` is not a cell
+/// reference.
+fn shift_reference(token: &str, rows: i64, cols: i64) -> Option<String> {
+    let (col_abs, col, row_abs, row) = parse_cell_reference(token)?;
+    let (col, row) = (col as i64, row as i64);
+    let new_col = if col_abs { col } else { col + cols };
+    let new_row = if row_abs { row } else { row + rows };
+    if !(XL_MIN_COL as i64..=XL_MAX_COL as i64).contains(&new_col) || !(1..=XL_MAX_ROW as i64).contains(&new_row) {
+        return Some("#REF!".to_owned())
+    }
+    Some(format!(
+        "{}{}{}{}",
+        if col_abs { "$" } else { "" },
+        num2col(new_col as u16)?,
+        if row_abs { "$" } else { "" },
+        new_row,
+    ))
+}
+
+/// Parse a single cell reference like "A1" or "$B$2" into (column is absolute, column number, row
+/// is absolute, row number). Returns `None` if `token` is not a valid cell reference.
+pub(crate) fn parse_cell_reference(token: &str) -> Option<(bool, u16, bool, u32)> {
+    let (col_abs, rest) = match token.strip_prefix('$') {
+        Some(rest) => (true, rest),
+        None => (false, token),
+    };
+    // the row's "$" (if any) has to come right after the column letters
+    let unmarked = rest.replacen('$', "", 1);
+    let (col, row) = split_reference(&unmarked)?;
+    let row_abs = unmarked.len() < rest.len();
+    if row_abs && !rest[col.len()..].starts_with('$') {
+        return None
+    }
+    Some((col_abs, col2num(col)?, row_abs, row))
+}
+
+/// The (raw) value of an attribute as a `String`. Bytes that are not valid UTF-8 are replaced with
+/// U+FFFD rather than causing a panic, since one bad byte should not stop us reading a workbook.
 pub fn attr_value(a: &Attribute) -> String {
-    String::from_utf8(a.value.to_vec()).unwrap()
+    String::from_utf8_lossy(&a.value).into_owned()
+}
+
+/// Skip the UTF-8 byte order mark that some programs write at the start of their xml parts.
+/// quick_xml does not expect one, and would otherwise hand it back as text (e.g., at the start of
+/// the first shared string). Wrap the source of every `Reader` in this.
+pub(crate) fn skip_bom<B: BufRead>(mut inner: B) -> B {
+    if inner.fill_buf().is_ok_and(|buf| buf.starts_with(b"\xEF\xBB\xBF")) {
+        inner.consume(3);
+    }
+    inner
+}
+
+/// Read the encoding declared in the prolog of an xml file (e.g., `<?xml encoding="UTF-8"?>`).
+/// Returns "UTF-8" (the xml default) if the file does not declare one. Only the start of the file
+/// is read, so give this a reader of its own.
+pub(crate) fn declared_encoding<B: BufRead>(reader: &mut Reader<B>) -> String {
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Decl(ref e)) => {
+                if let Some(Ok(encoding)) = e.encoding() {
+                    return String::from_utf8_lossy(&encoding).into_owned()
+                }
+                break
+            },
+            Ok(Event::Start(_)) | Ok(Event::Empty(_)) | Ok(Event::Eof) | Err(_) => break,
+            _ => (),
+        }
+        buf.clear();
+    }
+    "UTF-8".to_owned()
+}
+
+/// Turns the raw bytes of xml text into a `String`. Text is always treated as UTF-8 unless the
+/// `encoding` feature is enabled, in which case we use the encoding the xml declares (or the one
+/// the user asked for when opening the workbook).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TextDecoder {
+    #[cfg(feature = "encoding")]
+    encoding: &'static encoding_rs::Encoding,
+    /// Did the user pick the encoding? If so, we ignore what the xml declares.
+    #[cfg(feature = "encoding")]
+    forced: bool,
+}
+
+impl TextDecoder {
+    pub(crate) fn utf8() -> Self {
+        TextDecoder {
+            #[cfg(feature = "encoding")]
+            encoding: encoding_rs::UTF_8,
+            #[cfg(feature = "encoding")]
+            forced: false,
+        }
+    }
+
+    /// A decoder that always uses the encoding with the given `label` (e.g., "windows-1252").
+    /// Returns `None` if the label is not one `encoding_rs` knows about.
+    #[cfg(feature = "encoding")]
+    pub(crate) fn forced(label: &str) -> Option<Self> {
+        let encoding = encoding_rs::Encoding::for_label(label.as_bytes())?;
+        Some(TextDecoder { encoding, forced: true })
+    }
+
+    /// Switch to the encoding declared in an xml prolog (unless the user picked one already)
+    pub(crate) fn declared(&mut self, decl: &BytesDecl) {
+        if let Some(Ok(label)) = decl.encoding() {
+            self.declared_label(&label);
+        }
+    }
+
+    /// Switch to the encoding with the given `label` (unless the user picked one already)
+    #[cfg(feature = "encoding")]
+    pub(crate) fn declared_label(&mut self, label: &[u8]) {
+        if self.forced { return }
+        if let Some(encoding) = encoding_rs::Encoding::for_label(label) {
+            self.encoding = encoding;
+        }
+    }
+
+    #[cfg(not(feature = "encoding"))]
+    pub(crate) fn declared_label(&mut self, _label: &[u8]) {}
+
+    /// Decode `bytes` (replacing anything that cannot be decoded rather than failing)
+    pub(crate) fn decode(&self, bytes: &[u8]) -> String {
+        #[cfg(feature = "encoding")]
+        return self.encoding.decode_without_bom_handling(bytes).0.into_owned();
+        #[cfg(not(feature = "encoding"))]
+        return String::from_utf8_lossy(bytes).into_owned();
+    }
+
+    /// Decode `bytes` and then replace any xml escapes (e.g., "&amp;") in the result
+    pub(crate) fn unescape(&self, bytes: &[u8]) -> String {
+        let text = self.decode(bytes);
+        match escape::unescape(text.as_bytes()) {
+            Ok(Cow::Owned(unescaped)) => String::from_utf8_lossy(&unescaped).into_owned(),
+            _ => text,
+        }
+    }
 }
 
 pub fn get(attrs: Attributes, which: &[u8]) -> Option<String> {
@@ -45,6 +338,35 @@ pub fn get(attrs: Attributes, which: &[u8]) -> Option<String> {
     None
 }
 
+/// Turn the target of a relationship into the name of a part in the zip. Targets are relative to
+/// the folder of the part that owns the relationship (e.g., "../media/image1.png" from
+/// "xl/drawings/drawing1.xml" is "xl/media/image1.png"), unless they start with a "/".
+pub(crate) fn resolve_target(owner: &str, target: &str) -> String {
+    if let Some(absolute) = target.strip_prefix('/') {
+        return absolute.to_owned()
+    }
+    let mut path: Vec<&str> = owner.split('/').collect();
+    path.pop();
+    for piece in target.split('/') {
+        match piece {
+            ".." => { path.pop(); },
+            "." | "" => (),
+            _ => path.push(piece),
+        }
+    }
+    path.join("/")
+}
+
+/// The result of converting an Excel serial number into a date with `excel_number_to_date`. Excel
+/// does not distinguish between dates, times, and date-times (they are all just numbers), so we
+/// decide which one you get based on the serial number itself:
+///
+/// - a whole number (e.g., `44197.0`) is a `Date`
+/// - a number with only a fractional part (e.g., `0.5625`) is a `Time` of day
+/// - a number with both a whole and fractional part (e.g., `44197.5625`) is a `DateTime`
+/// - a number so negative it falls before the year 1, or so big it falls after 12/31/9999 (the last
+///   date Excel allows), is left as a `Number` (of days)
+#[derive(Debug, PartialEq)]
 pub enum DateConversion {
     Date(NaiveDate),
     DateTime(NaiveDateTime),
@@ -52,22 +374,37 @@ pub enum DateConversion {
     Number(i64),
 }
 
-///  Return date of "number" based on the date system provided.
+/// Return date of "number" based on the date system provided.
+///
+/// The date system is either the 1904 system or the 1900 system depending on which date system
+/// the spreadsheet is using. See <http://bit.ly/2He5HoD> for more information on date systems in
+/// Excel.
+///
+/// See `DateConversion` for how we decide whether `number` is a date, a time, or a date-time.
+/// Returns an `Err` if `number` does not correspond to a real date (i.e., the 2/29/1900 that
+/// Excel believes exists under the 1900 date system).
+///
+/// # Example usage
+///
+///     use chrono::{NaiveDate, NaiveTime};
+///     use xl::{excel_number_to_date, DateConversion, DateSystem};
+///
+///     let date = excel_number_to_date(44197.0, &DateSystem::V1900);
+///     assert_eq!(date, Ok(DateConversion::Date(NaiveDate::from_ymd_opt(2021, 1, 1).unwrap())));
 ///
-///  The date system is either the 1904 system or the 1900 system depending on which date system
-///  the spreadsheet is using. See <http://bit.ly/2He5HoD> for more information on date systems in
-///  Excel.
-pub fn excel_number_to_date(number: f64, date_system: &DateSystem) -> DateConversion {
+///     let time = excel_number_to_date(0.5625, &DateSystem::V1900);
+///     assert_eq!(time, Ok(DateConversion::Time(NaiveTime::from_hms_opt(13, 30, 0).unwrap())));
+pub fn excel_number_to_date(number: f64, date_system: &DateSystem) -> Result<DateConversion, String> {
     let base = match date_system {
         DateSystem::V1900 => {
             // Under the 1900 base system, 1 represents 1/1/1900 (so we start with a base date of
             // 12/31/1899).
-            let mut base = NaiveDate::from_ymd(1899, 12, 31).and_hms(0, 0, 0);
+            let mut base = date_system.base().and_hms_opt(0, 0, 0).unwrap();
             // BUT (!), Excel considers 1900 a leap-year which it is not. As such, it will happily
             // represent 2/29/1900 with the number 60, but we cannot convert that value to a date
-            // so we throw an error.
+            // so we return an error and let the caller decide what to do with the cell.
             if (number - 60.0).abs() < 0.0001 {
-                panic!("Bad date in Excel file - 2/29/1900 not valid")
+                return Err("Bad date in Excel file - 2/29/1900 not valid".to_owned())
             // Otherwise, if the value is greater than 60 we need to adjust the base date to
             // 12/30/1899 to account for this leap year bug.
             } else if number > 60.0 {
@@ -78,12 +415,12 @@ pub fn excel_number_to_date(number: f64, date_system: &DateSystem) -> DateConver
         DateSystem::V1904 => {
             // Under the 1904 system, 1 represent 1/2/1904 so we start with a base date of
             // 1/1/1904.
-            NaiveDate::from_ymd(1904, 1, 1).and_hms(0, 0, 0)
+            date_system.base().and_hms_opt(0, 0, 0).unwrap()
         }
     };
     let days = number.trunc() as i64;
-    if days < -693594 {
-        return DateConversion::Number(days)
+    if !(-693594..=2958465).contains(&days) {
+        return Ok(DateConversion::Number(days))
     }
     let partial_days = number - (days as f64);
     let seconds = (partial_days * 86400000.0).round() as i64;
@@ -91,18 +428,61 @@ pub fn excel_number_to_date(number: f64, date_system: &DateSystem) -> DateConver
     let seconds = Duration::seconds(seconds / 1000);
     let date = base + Duration::days(days) + seconds + milliseconds;
     if days == 0 {
-        DateConversion::Time(date.time())
-    } else if date.time() == NaiveTime::from_hms(0, 0, 0) {
-        DateConversion::Date(date.date())
+        Ok(DateConversion::Time(date.time()))
+    } else if date.time() == NaiveTime::from_hms_opt(0, 0, 0).unwrap() {
+        Ok(DateConversion::Date(date.date()))
     } else {
-        DateConversion::DateTime(date)
+        Ok(DateConversion::DateTime(date))
     }
 }
 
+/// Return the serial number Excel would use to store `date` under the date system provided. This
+/// is the inverse of `excel_number_to_date`.
+pub fn date_to_excel_number(date: NaiveDateTime, date_system: &DateSystem) -> f64 {
+    let mut days = (date.date() - date_system.base()).num_days();
+    if let DateSystem::V1900 = date_system {
+        // account for the fictional 2/29/1900 (see `excel_number_to_date`)
+        if days >= 60 { days += 1 }
+    }
+    days as f64 + time_to_excel_number(date.time())
+}
+
+/// Return the serial number Excel would use to store a time of day. Times are stored as the
+/// fraction of a day that has elapsed since midnight (so 12:00 PM is 0.5) and do not depend on the
+/// date system of the workbook.
+pub fn time_to_excel_number(time: NaiveTime) -> f64 {
+    let milliseconds = time.num_seconds_from_midnight() as f64 * 1000.0
+        + (time.nanosecond() / 1_000_000) as f64;
+    milliseconds / 86400000.0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn byte_order_mark() {
+        let mut reader = Reader::from_reader(skip_bom(&b"\xEF\xBB\xBF<a>text</a>"[..]));
+        reader.trim_text(true);
+        let mut buf = Vec::new();
+        assert!(matches!(reader.read_event(&mut buf), Ok(Event::Start(ref e)) if e.name() == b"a"));
+        assert_eq!(skip_bom(&b"<a/>"[..]), b"<a/>");
+        assert_eq!(skip_bom(&b"\xEF\xBB"[..]), b"\xEF\xBB");
+    }
+
+    #[test]
+    fn attr_value_invalid_utf8() {
+        let a = Attribute { key: b"r", value: Cow::Borrowed(b"A\xff1") };
+        assert_eq!(attr_value(&a), "A\u{fffd}1");
+    }
+
+    #[test]
+    fn resolve_targets() {
+        assert_eq!(resolve_target("xl/drawings/drawing1.xml", "../media/image1.png"), "xl/media/image1.png");
+        assert_eq!(resolve_target("xl/worksheets/sheet1.xml", "/xl/drawings/drawing1.xml"), "xl/drawings/drawing1.xml");
+        assert_eq!(resolve_target("xl/workbook.xml", "worksheets/sheet1.xml"), "xl/worksheets/sheet1.xml");
+    }
+
     #[test]
     fn num_to_letter_w() {
         assert_eq!(num2col(23), Some(String::from("W")));
@@ -168,8 +548,125 @@ mod tests {
         assert_eq!(col2num("12"), None);
     }
 
+    #[test]
+    fn letter_to_num_too_long() {
+        assert_eq!(col2num("SHEET"), None);
+    }
+
     #[test]
     fn letter_to_num_semicolon() {
         assert_eq!(col2num(";"), None);
     }
+
+    #[test]
+    fn split_references() {
+        assert_eq!(split_reference("A1"), Some(("A", 1)));
+        assert_eq!(split_reference("xfd1048576"), Some(("xfd", 1048576)));
+        assert_eq!(split_reference("XFE1"), Some(("XFE", 1)));
+        for bad in &["", "A", "1", "A0", "A1048577", "A1B", "A-1", "$A$1", "A1:B2", "É1"] {
+            assert_eq!(split_reference(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn cell_references() {
+        assert_eq!(parse_cell_reference("B2"), Some((false, 2, false, 2)));
+        assert_eq!(parse_cell_reference("$B$2"), Some((true, 2, true, 2)));
+        assert_eq!(parse_cell_reference("b$2"), Some((false, 2, true, 2)));
+        for bad in &["", "$", "B$", "B2$", "B$$2", "$$B2", "1$2", "XFE1", "B0"] {
+            assert_eq!(parse_cell_reference(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn range_refs() {
+        let range: RangeRef = "$B$2:c3".parse().unwrap();
+        assert_eq!(range, RangeRef { first_col: 2, first_row: 2, last_col: 3, last_row: 3 });
+        let cell: RangeRef = "B2".parse().unwrap();
+        assert_eq!((cell.width(), cell.height()), (1, 1));
+        assert_eq!(cell.to_string(), "B2");
+        for bad in &["", "B2:", ":B2", "C3:A1", "A3:C1", "A1:B2:C3", "nonsense"] {
+            assert!(bad.parse::<RangeRef>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn shift_relative_references() {
+        assert_eq!(shift_formula("A2*2", 1, 0), "A3*2");
+        assert_eq!(shift_formula("SUM(A1:B3)", 2, 1), "SUM(B3:C5)");
+        assert_eq!(shift_formula("$A$1+B1+$C1+D$1", 1, 1), "$A$1+C2+$C2+E$1");
+    }
+
+    #[test]
+    fn shift_leaves_names_and_literals() {
+        assert_eq!(shift_formula("LOG10(A1)&\"A1\"", 1, 0), "LOG10(A2)&\"A1\"");
+        assert_eq!(shift_formula("'Q1 A1'!A1+Sheet2!B2", 1, 0), "'Q1 A1'!A2+Sheet2!B3");
+        assert_eq!(shift_formula("1.5E+10*A1", 0, 1), "1.5E+10*B1");
+    }
+
+    #[test]
+    fn shift_off_sheet() {
+        assert_eq!(shift_formula("A1", -1, 0), "#REF!");
+    }
+
+    #[test]
+    fn leap_year_bug_is_error() {
+        assert!(excel_number_to_date(60.0, &DateSystem::V1900).is_err());
+    }
+
+    #[test]
+    fn after_leap_year_bug() {
+        match excel_number_to_date(61.0, &DateSystem::V1900) {
+            Ok(DateConversion::Date(d)) => assert_eq!(d, NaiveDate::from_ymd_opt(1900, 3, 1).unwrap()),
+            _ => panic!("expected 3/1/1900"),
+        }
+    }
+
+    #[test]
+    fn conversion_kinds() {
+        let date = NaiveDate::from_ymd_opt(2021, 1, 1).unwrap();
+        assert_eq!(excel_number_to_date(44197.0, &DateSystem::V1900), Ok(DateConversion::Date(date)));
+        assert_eq!(excel_number_to_date(44197.5, &DateSystem::V1900), Ok(DateConversion::DateTime(date.and_hms_opt(12, 0, 0).unwrap())));
+        assert_eq!(excel_number_to_date(0.5, &DateSystem::V1900), Ok(DateConversion::Time(NaiveTime::from_hms_opt(12, 0, 0).unwrap())));
+        assert_eq!(excel_number_to_date(-700000.0, &DateSystem::V1900), Ok(DateConversion::Number(-700000)));
+        assert_eq!(excel_number_to_date(2958465.0, &DateSystem::V1900), Ok(DateConversion::Date(NaiveDate::from_ymd_opt(9999, 12, 31).unwrap())));
+        assert_eq!(excel_number_to_date(1e15, &DateSystem::V1904), Ok(DateConversion::Number(1_000_000_000_000_000)));
+    }
+
+    #[test]
+    fn time_round_trip() {
+        let time = NaiveTime::from_hms_opt(13, 30, 0).unwrap();
+        let serial = time_to_excel_number(time);
+        assert_eq!(serial, 0.5625);
+        match excel_number_to_date(serial, &DateSystem::V1900) {
+            Ok(DateConversion::Time(t)) => assert_eq!(t, time),
+            _ => panic!("expected 13:30:00"),
+        }
+    }
+
+    #[test]
+    fn datetime_round_trip() {
+        for date_system in &[DateSystem::V1900, DateSystem::V1904] {
+            let date = NaiveDate::from_ymd_opt(2021, 7, 4).unwrap().and_hms_opt(13, 30, 0).unwrap();
+            let serial = date_to_excel_number(date, date_system);
+            match excel_number_to_date(serial, date_system) {
+                Ok(DateConversion::DateTime(d)) => assert_eq!(d, date),
+                _ => panic!("expected 2021-07-04 13:30:00"),
+            }
+        }
+    }
+
+    #[test]
+    fn date_to_number_around_leap_year_bug() {
+        assert_eq!(date_to_excel_number(NaiveDate::from_ymd_opt(1900, 2, 28).unwrap().and_hms_opt(0, 0, 0).unwrap(), &DateSystem::V1900), 59.0);
+        assert_eq!(date_to_excel_number(NaiveDate::from_ymd_opt(1900, 3, 1).unwrap().and_hms_opt(0, 0, 0).unwrap(), &DateSystem::V1900), 61.0);
+    }
+
+    #[test]
+    fn before_leap_year_bug() {
+        match excel_number_to_date(59.0, &DateSystem::V1900) {
+            Ok(DateConversion::Date(d)) => assert_eq!(d, NaiveDate::from_ymd_opt(1900, 2, 28).unwrap()),
+            _ => panic!("expected 2/28/1900"),
+        }
+    }
 }
@@ -2,14 +2,18 @@
 //! entire file).
 
 use std::collections::HashMap;
-use std::fs;
-use std::fs::File;
-use std::io::BufReader;
+use std::fmt;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use chrono::NaiveDate;
 use quick_xml::Reader;
 use quick_xml::events::Event;
 use zip::ZipArchive;
-use crate::ws::{SheetReader, Worksheet};
-use crate::utils;
+use crate::error::XlError;
+use crate::properties::DocProperties;
+use crate::ws::{SheetKind, SheetReader, SheetState, Worksheet};
+use crate::utils::{self, TextDecoder};
 
 /// Excel spreadsheets support two different date systems:
 ///
@@ -22,12 +26,33 @@ use crate::utils;
 /// number represents unless you also know the date system the spreadsheet uses.
 ///
 /// See <https://tinyurl.com/4syjy6cw> for more information.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum DateSystem {
     V1900,
     V1904,
 }
 
+impl DateSystem {
+    /// The date represented by serial number 0 under this date system. Time-only values (serials
+    /// between 0 and 1) are measured from this date under either system, so a time of day never
+    /// drifts onto a real date. This is useful if you want to do your own serial number math (but
+    /// see `xl::excel_number_to_date` and `xl::date_to_excel_number` first).
+    ///
+    /// # Example usage
+    ///
+    ///     use chrono::NaiveDate;
+    ///     use xl::DateSystem;
+    ///
+    ///     assert_eq!(DateSystem::V1900.base(), NaiveDate::from_ymd_opt(1899, 12, 31).unwrap());
+    ///     assert_eq!(DateSystem::V1904.base(), NaiveDate::from_ymd_opt(1904, 1, 1).unwrap());
+    pub fn base(&self) -> NaiveDate {
+        match self {
+            DateSystem::V1900 => NaiveDate::from_ymd_opt(1899, 12, 31).unwrap(),
+            DateSystem::V1904 => NaiveDate::from_ymd_opt(1904, 1, 1).unwrap(),
+        }
+    }
+}
+
 /// The Workbook is the primary object you will use in this module. The public interface allows you
 /// to see the path of the workbook as well as its date system.
 ///
@@ -36,14 +61,124 @@ pub enum DateSystem {
 ///     use xl::Workbook;
 ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
 ///
+/// # Threads
+///
+/// A `Workbook` is `Send`, so you can open it on one thread and move it to a worker thread to do
+/// the actual reading. It is also `Sync`, but that is of limited use because almost everything you
+/// will want to do with a workbook (e.g., `sheets` or `Worksheet::rows`) needs a `&mut Workbook`.
+/// If you want several threads to share one workbook, wrap it in a `Mutex`. A `RowIter` borrows
+/// the workbook it was created from, so the workbook cannot move to another thread while any of
+/// its rows are being read.
+///
+///     use std::thread;
+///     use xl::Workbook;
+///
+///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+///     let handle = thread::spawn(move || wb.sheets().len());
+///     assert_eq!(handle.join().unwrap(), 4);
 #[derive(Debug)]
 pub struct Workbook {
+    /// The path the workbook was opened from (empty if it was opened with `open_reader`)
     pub path: String,
-    xls: ZipArchive<fs::File>,
-    // encoding: String,
+    xls: ZipArchive<Box<dyn Source>>,
+    /// The name of the main workbook part (usually "xl/workbook.xml")
+    pub(crate) main_part: String,
+    /// The encoding the workbook xml says it uses (e.g., "UTF-8"). Excel always writes UTF-8, but
+    /// files written by other programs sometimes declare something else. Text is only decoded
+    /// with this encoding when the `encoding` feature is enabled (otherwise it is read as UTF-8).
+    pub encoding: String,
     pub date_system: DateSystem,
-    strings: Vec<String>,
-    styles: Vec<String>,
+    strings: Arc<[String]>,
+    styles: Arc<[String]>,
+    decoder: TextDecoder,
+    /// The decoder the workbook was opened with (if any), so `reload` can use it again
+    forced_decoder: Option<TextDecoder>,
+    /// Were the shared strings given to us rather than read from the file?
+    supplied_strings: bool,
+}
+
+/// How long each step of opening a workbook took, and how big the parts it read are (see
+/// `Workbook::open_with_stats`). The steps happen in the order of the fields.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpenStats {
+    /// Opening the zip and finding the workbook's parts
+    pub zip_open: Duration,
+    /// Reading the shared strings
+    pub strings: Duration,
+    /// The (uncompressed) size of the shared strings part in bytes (0 if there is none)
+    pub strings_bytes: u64,
+    /// Reading the number formats of the styles
+    pub styles: Duration,
+    /// The (uncompressed) size of the styles part in bytes (0 if there is none)
+    pub styles_bytes: u64,
+    /// Finding out which date system the workbook uses
+    pub date_system: Duration,
+}
+
+impl OpenStats {
+    /// The time all of the steps took together
+    pub fn total(&self) -> Duration {
+        self.zip_open + self.strings + self.styles + self.date_system
+    }
+}
+
+/// Times the steps of opening a workbook for `OpenStats`. It only reads the clock when it is asked
+/// to, since there is no clock on some platforms (e.g., wasm32-unknown-unknown).
+struct Stopwatch(Option<Instant>);
+
+impl Stopwatch {
+    fn start(timing: bool) -> Self {
+        Stopwatch(if timing { Some(Instant::now()) } else { None })
+    }
+
+    /// The time since the last lap (or since the start), restarting the clock
+    fn lap(&mut self) -> Duration {
+        match &mut self.0 {
+            Some(since) => {
+                let now = Instant::now();
+                let lap = now - *since;
+                *since = now;
+                lap
+            },
+            None => Duration::default(),
+        }
+    }
+}
+
+/// Anything a workbook can be read from: a file, or the bytes of one (see `Workbook::open_reader`)
+trait Source: Read + Seek + Send + Sync {}
+
+impl<R: Read + Seek + Send + Sync> Source for R {}
+
+impl fmt::Debug for dyn Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Source")
+    }
+}
+
+/// One of the files (parts) inside the xlsx zip, as returned by `Workbook::parts`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartInfo {
+    /// The path of the part within the zip (e.g., "xl/worksheets/sheet1.xml")
+    pub name: String,
+    /// The uncompressed size of the part in bytes
+    pub size: u64,
+    /// Is this entry a directory rather than a file?
+    pub is_dir: bool,
+}
+
+/// A relationship from one part of the workbook to another (e.g., from a worksheet to the drawing
+/// that holds its images).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Relationship {
+    pub id: String,
+    /// The relationship type, e.g., "http://schemas.openxmlformats.org/officeDocument/2006/relationships/drawing"
+    pub kind: String,
+    /// The name of the part in the zip (already resolved, e.g., "xl/drawings/drawing1.xml"), or
+    /// the address of something outside the workbook (see `external`)
+    pub target: String,
+    /// Does the relationship point outside the workbook (e.g., a hyperlink to a web page)?
+    pub external: bool,
 }
 
 /// A `SheetMap` is an object containing all the sheets in a given workbook. The only way to obtain
@@ -59,6 +194,8 @@ pub struct Workbook {
 pub struct SheetMap {
     sheets_by_name: HashMap::<String, u8>,
     sheets_by_num: Vec<Option<Worksheet>>,
+    /// The (1-based) position of the sheet that was active when the workbook was saved
+    active: Option<u8>,
 }
 
 impl SheetMap {
@@ -96,14 +233,14 @@ pub enum SheetNameOrNum<'a> {
 
 /// Trait to make it easy to use `get` when trying to get a sheet. You will probably not use this
 /// struct directly.
-pub trait SheetAccessTrait { fn go(&self) -> SheetNameOrNum; }
+pub trait SheetAccessTrait { fn go(&self) -> SheetNameOrNum<'_>; }
 
 impl SheetAccessTrait for &str {
-    fn go(&self) -> SheetNameOrNum { SheetNameOrNum::Name(*self) }
+    fn go(&self) -> SheetNameOrNum<'_> { SheetNameOrNum::Name(self) }
 }
 
 impl SheetAccessTrait for usize {
-    fn go(&self) -> SheetNameOrNum { SheetNameOrNum::Pos(*self) }
+    fn go(&self) -> SheetNameOrNum<'_> { SheetNameOrNum::Pos(*self) }
 }
 
 impl SheetMap {
@@ -115,6 +252,16 @@ impl SheetMap {
     /// consistent with VBA. It's possible it may change in the future, but it seems intuitive
     /// enough if you are familiar with VBA and Excel programming, so it may not.
     ///
+    /// Positions count *every* sheet in the workbook, hidden or not (like `Worksheets(n)` in VBA).
+    /// If you want the nth tab a user would see in Excel, use `get_visible` instead.
+    ///
+    /// Names are matched exactly first. If no sheet has exactly that name, we look for one whose
+    /// name only differs in case (e.g., "sheet1" finds "Sheet1"), as Excel does, or in the spaces
+    /// at its start or end (e.g., a name pasted as " Sheet1 "). Spaces inside a name still have to
+    /// match. Excel does not let two sheets have names that only differ in case, but if a file
+    /// has them anyway (or two names only differ in their surrounding spaces), neither one is
+    /// returned.
+    ///
     /// # Example usage
     ///
     ///     use xl::{Workbook, Worksheet};
@@ -126,6 +273,9 @@ impl SheetMap {
     ///     let time_sheet = sheets.get("Time");
     ///     assert!(time_sheet.is_some());
     ///
+    ///     // names are not case sensitive
+    ///     assert_eq!(sheets.get("TIME").unwrap().name, "Time");
+    ///
     ///     // unknown sheet name
     ///     let unknown_sheet = sheets.get("not in this workbook");
     ///     assert!(unknown_sheet.is_none());
@@ -137,15 +287,118 @@ impl SheetMap {
         let sheet = sheet.go();
         match sheet {
             SheetNameOrNum::Name(n) => {
-                match self.sheets_by_name.get(n) {
-                    Some(p) => self.sheets_by_num.get(*p as usize)?.as_ref(),
-                    None => None
-                }
+                let p = match self.sheets_by_name.get(n) {
+                    Some(p) => *p,
+                    None => self.loose_position(n)?,
+                };
+                self.sheets_by_num.get(p as usize)?.as_ref()
             },
             SheetNameOrNum::Pos(n) => self.sheets_by_num.get(n)?.as_ref(),
         }
     }
 
+    /// The position of the one sheet whose name matches `name` without regard to case or the
+    /// whitespace around either name, or `None` if there is no such sheet (or more than one)
+    fn loose_position(&self, name: &str) -> Option<u8> {
+        let name = name.trim().to_lowercase();
+        let mut matches = self.sheets_by_name.iter().filter(|(n, _)| n.trim().to_lowercase() == name);
+        match (matches.next(), matches.next()) {
+            (Some((_, p)), None) => Some(*p),
+            _ => None,
+        }
+    }
+
+    /// Like `get` with a position, but using the **0-based** indexing you are used to from the rest
+    /// of Rust: `get_zero_based(0)` is the first sheet in the workbook (i.e., `get(1)`).
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     assert_eq!(sheets.get_zero_based(0).unwrap().name, "Sheet1");
+    ///     assert!(sheets.get_zero_based(4).is_none());
+    pub fn get_zero_based(&self, n: usize) -> Option<&Worksheet> {
+        self.get(n.checked_add(1)?)
+    }
+
+    /// Get a sheet by its position among the *visible* sheets only, i.e., by its place in the tab
+    /// strip a user sees in Excel. Like `get`, positions are **1-based**, so `get_visible(1)` is
+    /// the first visible tab. Hidden and very hidden sheets (see `SheetState`) do not take up a
+    /// position here, so `get_visible(n)` and `get(n)` are different sheets whenever a hidden
+    /// sheet comes before the nth one.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     // tabs: First, Secret (hidden), Second, Vault (very hidden), Third
+    ///     let mut wb = Workbook::open("tests/data/hidden_tabs.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     assert_eq!(sheets.get(2).unwrap().name, "Secret");
+    ///     assert_eq!(sheets.get_visible(2).unwrap().name, "Second");
+    ///     assert_eq!(sheets.get_visible(3).unwrap().name, "Third");
+    ///     assert!(sheets.get_visible(4).is_none());
+    pub fn get_visible(&self, pos: usize) -> Option<&Worksheet> {
+        self.worksheets()
+            .filter(|ws| ws.state == SheetState::Visible)
+            .nth(pos.checked_sub(1)?)
+    }
+
+    /// Iterate over the sheets in the workbook (in order) along with their position. The position
+    /// is **1-based**, so it is the number you would pass to `get` to find the same sheet.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let (pos, ws) = sheets.iter().nth(2).unwrap();
+    ///     assert_eq!(pos, 3);
+    ///     assert_eq!(ws.name, "Time");
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Worksheet)> {
+        self.sheets_by_num
+            .iter()
+            .enumerate()
+            .filter_map(|(pos, s)| Some((pos, s.as_ref()?)))
+    }
+
+    /// Iterate over the worksheets in the workbook (in order). A `SheetMap` does not borrow the
+    /// workbook it came from, so you can read each worksheet as you go.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let mut total_rows = 0;
+    ///     for ws in sheets.worksheets() {
+    ///         total_rows += ws.rows(&mut wb).unwrap().count();
+    ///     }
+    ///     assert!(total_rows > 0);
+    pub fn worksheets(&self) -> impl Iterator<Item = &Worksheet> {
+        self.sheets_by_num.iter().flatten()
+    }
+
+    /// Like `worksheets`, but leaving out chart sheets and any other sheets that have no cells to
+    /// read (see `SheetKind`).
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/chartsheet.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     assert_eq!(sheets.by_name(), vec!["Data", "Chart", "Dialog"]);
+    ///     let names: Vec<&str> = sheets.data_sheets().map(|ws| &ws.name[..]).collect();
+    ///     assert_eq!(names, vec!["Data"]);
+    pub fn data_sheets(&self) -> impl Iterator<Item = &Worksheet> {
+        self.worksheets().filter(|ws| ws.kind() == SheetKind::Worksheet)
+    }
+
     /// The number of active sheets in the workbook.
     ///
     /// # Example usage
@@ -158,92 +411,64 @@ impl SheetMap {
     pub fn len(&self) -> u8 {
         (self.sheets_by_num.len() - 1) as u8
     }
+
+    /// The sheet that was active (i.e., selected) when the workbook was last saved, which is the
+    /// sheet Excel shows first when you open the file. Returns `None` if the workbook does not
+    /// record a view.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     assert_eq!(sheets.active().unwrap().name, "Time");
+    pub fn active(&self) -> Option<&Worksheet> {
+        self.get(self.active? as usize)
+    }
 }
 
 impl Workbook {
     /// xlsx zips contain an xml file that has a mapping of "ids" to "targets." The ids are used
     /// to uniquely identify sheets within the file. The targets have information on where the
-    /// sheets can be found within the zip. This function returns a hashmap of id -> target so that
+    /// sheets can be found within the zip. This function returns a hashmap of id -> relationship
+    /// (with the name of the part in the zip as its target, and the kind of part it is) so that
     /// you can quickly determine the name of the sheet xml file within the zip.
-    fn rels(&mut self) -> HashMap<String, String> {
-        let mut map = HashMap::new();
-        match self.xls.by_name("xl/_rels/workbook.xml.rels") {
-            Ok(rels) => {
-                // Looking for tree structure like:
-                //   Relationships
-                //     Relationship(id = "abc", target = "def")
-                //     Relationship(id = "ghi", target = "lkm")
-                //     etc.
-                //  Each relationship contains an id that is used to reference
-                //  the sheet and a target which tells us where we can find the
-                //  sheet in the zip file.
-                //
-                //  Uncomment the following line to print out a copy of what
-                //  the xml looks like (will probably not be too big).
-                // let _ = std::io::copy(&mut rels, &mut std::io::stdout());
-
-                let reader = BufReader::new(rels);
-                let mut reader = Reader::from_reader(reader);
-                reader.trim_text(true);
-
-                let mut buf = Vec::new();
-                loop {
-                    match reader.read_event(&mut buf) {
-                        Ok(Event::Empty(ref e)) if e.name() == b"Relationship" => {
-                            let mut id = String::new();
-                            let mut target = String::new();
-                            e.attributes()
-                                .for_each(|a| {
-                                    let a = a.unwrap();
-                                    if a.key == b"Id" {
-                                        id = utils::attr_value(&a);
-                                    }
-                                    if a.key == b"Target" {
-                                        target = utils::attr_value(&a);
-                                    }
-                                });
-                            map.insert(id, target);
-                        },
-                        Ok(Event::Eof) => break, // exits the loop when reaching end of file
-                        Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
-                        _ => (), // There are several other `Event`s we do not consider here
-                    }
-                    buf.clear();
-                }
-
-                map
-            },
-            Err(_) => map
-        }
+    fn rels(&mut self) -> HashMap<String, Relationship> {
+        let main_part = self.main_part.clone();
+        self.part_rels(&main_part).into_iter().map(|rel| (rel.id.clone(), rel)).collect()
     }
 
     /// Return `SheetMap` of all sheets in this workbook. See `SheetMap` class and associated
     /// methods for more detailed documentation.
     pub fn sheets(&mut self) -> SheetMap {
         let rels = self.rels();
-        let num_sheets = rels.iter().filter(|(_, v)| v.starts_with("worksheet")).count();
+        let num_sheets = rels.values().filter(|rel| !rel.external && rel.target.contains("sheets/")).count();
         let mut sheets = SheetMap {
             sheets_by_name: HashMap::new(),
             sheets_by_num: Vec::with_capacity(num_sheets + 1),
+            active: None,
         };
         sheets.sheets_by_num.push(None); // never a "0" sheet (consistent with VBA)
+        let decoder = self.decoder;
 
-        match self.xls.by_name("xl/workbook.xml") {
+        match self.xls.by_name(&self.main_part) {
             Ok(wb) => {
                 // let _ = std::io::copy(&mut wb, &mut std::io::stdout());
                 let reader = BufReader::new(wb);
-                let mut reader = Reader::from_reader(reader);
+                let mut reader = Reader::from_reader(utils::skip_bom(reader));
                 reader.trim_text(true);
 
                 let mut buf = Vec::new();
                 let mut current_sheet_num: u8 = 0;
                 loop {
                     match reader.read_event(&mut buf) {
-                        Ok(Event::Empty(ref e)) if e.name() == b"sheet" => {
+                        Ok(Event::Empty(ref e)) | Ok(Event::Start(ref e)) if e.name() == b"sheet" => {
                             current_sheet_num += 1;
                             let mut name = String::new();
                             let mut id = String::new();
                             let mut num = 0;
+                            let mut state = SheetState::Visible;
                             e.attributes()
                                 .for_each(|a| {
                                     let a = a.unwrap();
@@ -251,26 +476,33 @@ impl Workbook {
                                         id = utils::attr_value(&a);
                                     }
                                     if a.key == b"name" {
-                                        name = utils::attr_value(&a);
+                                        name = decoder.unescape(&a.value);
                                     }
                                     if a.key == b"sheetId" {
                                         if let Ok(r) = utils::attr_value(&a).parse() {
                                             num = r;
                                         }
                                     }
+                                    if a.key == b"state" {
+                                        state = SheetState::from_attr(&utils::attr_value(&a));
+                                    }
                                 });
                             sheets.sheets_by_name.insert(name.clone(), current_sheet_num);
-                            let target = {
-                                let s = rels.get(&id).unwrap();
-                                if let Some(stripped) = s.strip_prefix('/') {
-                                    stripped.to_string()
-                                } else {
-                                    "xl/".to_owned() + s
-                                }
-                            };
-                            let ws = Worksheet::new(name, current_sheet_num, target);
+                            let rel = rels.get(&id).unwrap();
+                            let mut ws = Worksheet::new(name, current_sheet_num, rel.target.clone());
+                            ws.state = state;
+                            ws.kind = SheetKind::from_rel_type(&rel.kind);
                             sheets.sheets_by_num.push(Some(ws));
                         },
+                        // activeTab is 0-based and defaults to the first sheet; only the first
+                        // view counts if there are several
+                        Ok(Event::Empty(ref e)) | Ok(Event::Start(ref e))
+                            if e.name() == b"workbookView" && sheets.active.is_none() => {
+                            let tab = utils::get(e.attributes(), b"activeTab")
+                                .and_then(|t| t.parse::<u8>().ok())
+                                .unwrap_or(0);
+                            sheets.active = tab.checked_add(1);
+                        },
                         Ok(Event::Eof) => {
                             break
                         },
@@ -285,6 +517,42 @@ impl Workbook {
         }
     }
 
+    /// Return the names of the sheets in this workbook (in the order they appear in Excel). This
+    /// only reads the sheet entries in the workbook xml, so it is cheaper than `sheets` when all
+    /// you want is a list of the tabs.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     assert_eq!(wb.sheet_names(), vec!["Sheet1", "Sheet2", "Time", "Sheet3"]);
+    pub fn sheet_names(&mut self) -> Vec<String> {
+        let mut names = Vec::new();
+        let decoder = self.decoder;
+        if let Ok(wb) = self.xls.by_name(&self.main_part) {
+            let reader = BufReader::new(wb);
+            let mut reader = Reader::from_reader(utils::skip_bom(reader));
+            reader.trim_text(true);
+            let mut buf = Vec::new();
+            loop {
+                match reader.read_event(&mut buf) {
+                    Ok(Event::Empty(ref e)) | Ok(Event::Start(ref e)) if e.name() == b"sheet" => {
+                        for a in e.attributes().flatten() {
+                            if a.key == b"name" {
+                                names.push(decoder.unescape(&a.value));
+                            }
+                        }
+                    },
+                    Ok(Event::Eof) | Err(_) => break,
+                    _ => (),
+                }
+                buf.clear();
+            }
+        }
+        names
+    }
+
     /// Open an existing workbook (xlsx file). Returns a `Result` in case there is an error opening
     /// the workbook.
     ///
@@ -300,84 +568,584 @@ impl Workbook {
     ///     assert!(wb.is_err());
     ///
     ///     // non-xlsx file
-    ///     let mut wb = Workbook::open("src/main.rs");
+    ///     let mut wb = Workbook::open("src/lib.rs");
     ///     assert!(wb.is_err());
     pub fn new(path: &str) -> Result<Self, String> {
+        Workbook::new_with_decoder(path, None, None, None).map_err(|e| e.to_string())
+    }
+
+    /// Open a workbook using `strings` as its shared strings instead of reading them from the
+    /// file. Reading the shared strings is usually the slowest part of opening a workbook, so if
+    /// you process many files that were generated from the same template, you can read the
+    /// strings once (see `Workbook::shared_strings`) and hand them to every other file.
+    ///
+    /// Only do this when you know the files have *identical* shared strings. Cells refer to their
+    /// text by position in the list, so a file whose strings differ (even only in their order) will
+    /// quietly give you the wrong text. A cell whose index is past the end of `strings` gets its
+    /// raw value (the index itself) as its text.
+    ///
+    /// # Example usage:
+    ///
+    ///     use xl::{ExcelValue, Workbook};
+    ///
+    ///     use std::sync::Arc;
+    ///
+    ///     let template = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let strings: Arc<[String]> = template.shared_strings().into();
+    ///     // cloning the `Arc` (rather than the strings) for each file is cheap
+    ///     let mut wb = Workbook::with_shared_strings("tests/data/Book1.xlsx", strings.clone()).unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let row10 = ws.rows(&mut wb).unwrap().nth(9).unwrap();
+    ///     assert_eq!(row10[7].value, ExcelValue::String("Test".into()));
+    pub fn with_shared_strings<S: Into<Arc<[String]>>>(path: &str, strings: S) -> Result<Self, XlError> {
+        Workbook::new_with_decoder(path, None, Some(strings.into()), None)
+    }
+
+    /// The shared strings of this workbook (the text that cells of type "s" refer to by
+    /// position).
+    pub fn shared_strings(&self) -> &[String] {
+        &self.strings
+    }
+
+    /// The shared string at `index` (the number a cell of type "s" has as its `raw_value`), or
+    /// `None` if there is no string at that position. Handy for checking which text a cell points
+    /// at when it does not show what you expect.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let row10 = ws.rows(&mut wb).unwrap().nth(9).unwrap();
+    ///     let index = row10[7].raw_value.parse().unwrap();
+    ///     assert_eq!(wb.shared_string(index), Some("Test"));
+    ///     assert_eq!(wb.shared_string(wb.shared_string_count()), None);
+    pub fn shared_string(&self, index: usize) -> Option<&str> {
+        self.strings.get(index).map(|s| s.as_str())
+    }
+
+    /// How many shared strings the workbook has
+    pub fn shared_string_count(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// The number format code of the style at `index`, or `None` if there is no style at that
+    /// position. This is the list `Cell::style` is looked up in, so you can use it to do your own
+    /// formatting. Built-in formats are given as their codes (e.g., style 0 is usually "General").
+    ///
+    /// Styles whose number format we do not know (neither a built-in format nor one the workbook
+    /// defines) are left out of the list, so after the first such style `index` no longer matches
+    /// the number in the `s` attribute of a cell in the worksheet xml.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let wb = Workbook::open("tests/data/formatted.xlsx").unwrap();
+    ///     assert_eq!(wb.style_count(), 5);
+    ///     assert_eq!(wb.style_code(0), Some("General"));
+    ///     assert_eq!(wb.style_code(2), Some("d-mmm-yy"));
+    ///     assert_eq!(wb.style_code(5), None);
+    pub fn style_code(&self, index: usize) -> Option<&str> {
+        self.styles.get(index).map(|s| s.as_str())
+    }
+
+    /// How many styles the workbook has, leaving out those whose number format we do not know (see
+    /// `style_code`)
+    pub fn style_count(&self) -> usize {
+        self.styles.len()
+    }
+
+    /// Open a workbook whose text is in the encoding with the given `label` (e.g.,
+    /// "windows-1252"), whatever the workbook itself claims. Excel always writes UTF-8, so you
+    /// should only need this for files written by other programs. Without this, text is decoded
+    /// with the encoding each xml file declares (see `Workbook::encoding`).
+    ///
+    /// This is only available with the `encoding` feature.
+    #[cfg(feature = "encoding")]
+    pub fn open_with_encoding(path: &str, label: &str) -> Result<Self, String> {
+        match TextDecoder::forced(label) {
+            Some(decoder) => Workbook::new_with_decoder(path, Some(decoder), None, None).map_err(|e| e.to_string()),
+            None => Err(format!("unknown encoding: {}", label)),
+        }
+    }
+
+    /// Open a workbook from anything you can read and seek in, such as the bytes of an xlsx
+    /// file you already have in memory (wrapped in a `std::io::Cursor`). This never touches the
+    /// file system, so it is also the way to open workbooks on platforms that do not have one
+    /// (e.g., `wasm32-unknown-unknown`). The workbook's `path` is empty, so it cannot be
+    /// `reload`ed.
+    ///
+    /// # Example usage
+    ///
+    ///     use std::io::Cursor;
+    ///     use xl::Workbook;
+    ///
+    ///     let bytes = std::fs::read("tests/data/Book1.xlsx").unwrap();
+    ///     let mut wb = Workbook::open_reader(Cursor::new(bytes)).unwrap();
+    ///     assert_eq!(wb.sheets().len(), 4);
+    pub fn open_reader<R: Read + Seek + Send + Sync + 'static>(reader: R) -> Result<Self, XlError> {
+        Workbook::from_source("", Box::new(reader), None, None, None)
+    }
+
+    /// Like `open`, but also returns how long each step of opening the workbook took and how big
+    /// the parts it read are (see `OpenStats`). Handy for finding out where the time goes when
+    /// you open many workbooks (it is usually the shared strings). The workbook itself is exactly
+    /// the same as the one `open` gives you.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let (mut wb, stats) = Workbook::open_with_stats("tests/data/Book1.xlsx").unwrap();
+    ///     assert_eq!(wb.sheets().len(), 4);
+    ///     assert!(stats.strings_bytes > 0);
+    ///     assert!(stats.total() >= stats.strings);
+    pub fn open_with_stats(path: &str) -> Result<(Self, OpenStats), XlError> {
+        let mut stats = OpenStats::default();
+        let wb = Workbook::new_with_decoder(path, None, None, Some(&mut stats))?;
+        Ok((wb, stats))
+    }
+
+    /// Open the workbook at `path`, using `decoder` (if given) for all of its text and `strings`
+    /// (if given) instead of the shared strings in the file. If `stats` is given, it is filled in
+    /// with the time each step took.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn new_with_decoder(
+        path: &str,
+        decoder: Option<TextDecoder>,
+        strings: Option<Arc<[String]>>,
+        stats: Option<&mut OpenStats>,
+    ) -> Result<Self, XlError> {
         if !std::path::Path::new(&path).exists() {
             let err = format!("'{}' does not exist", &path);
-            return Err(err);
+            return Err(XlError::Open(err));
         }
-        let zip_file = match fs::File::open(&path) {
+        let zip_file = match std::fs::File::open(path) {
             Ok(z) => z,
-            Err(e) => return Err(e.to_string()),
+            Err(e) => return Err(XlError::Open(e.to_string())),
         };
-        match zip::ZipArchive::new(zip_file) {
+        Workbook::from_source(path, Box::new(zip_file), decoder, strings, stats)
+    }
+
+    /// There is no file system to open `path` from, so this always fails (see `open_reader`).
+    #[cfg(target_arch = "wasm32")]
+    fn new_with_decoder(
+        path: &str,
+        _decoder: Option<TextDecoder>,
+        _strings: Option<Arc<[String]>>,
+        _stats: Option<&mut OpenStats>,
+    ) -> Result<Self, XlError> {
+        Err(XlError::Open(format!("cannot open '{}' on this platform (use Workbook::open_reader)", path)))
+    }
+
+    /// Read the workbook in `source` (which was opened from `path`, if it came from a file). See
+    /// `new_with_decoder` for `decoder`, `strings`, and `stats`.
+    fn from_source(
+        path: &str,
+        mut source: Box<dyn Source>,
+        decoder: Option<TextDecoder>,
+        strings: Option<Arc<[String]>>,
+        stats: Option<&mut OpenStats>,
+    ) -> Result<Self, XlError> {
+        let mut watch = Stopwatch::start(stats.is_some());
+        let mut timings = OpenStats::default();
+        let legacy_xls = is_legacy_xls(&mut source);
+        if let Err(e) = source.seek(SeekFrom::Start(0)) {
+            return Err(XlError::Open(e.to_string()))
+        }
+        let forced_decoder = decoder;
+        let supplied_strings = strings.is_some();
+        match zip::ZipArchive::new(source) {
             Ok(mut xls) => {
-                let strings = strings(&mut xls);
-                let styles = find_styles(&mut xls);
-                let date_system = get_date_system(&mut xls);
+                let main_part = main_part(&mut xls);
+                if let Some(content_type) = content_type(&mut xls, &main_part) {
+                    if !is_spreadsheet(&content_type) {
+                        return Err(XlError::NotSpreadsheet(path.to_owned(), content_type))
+                    }
+                }
+                // the workbook xml is read once for both its declared encoding and its date system
+                let workbook_xml = match read_part(&mut xls, &main_part) {
+                    Some(xml) => xml,
+                    None => return Err(XlError::MissingPart(main_part)),
+                };
+                let mut workbook_reader = Reader::from_reader(utils::skip_bom(&workbook_xml[..]));
+                workbook_reader.trim_text(true);
+                let encoding = utils::declared_encoding(&mut workbook_reader);
+                let decoder = decoder.unwrap_or_else(|| {
+                    let mut decoder = TextDecoder::utf8();
+                    decoder.declared_label(encoding.as_bytes());
+                    decoder
+                });
+                // the shared strings and styles are found through the workbook's relationships, but
+                // we fall back to their usual names next to the workbook part
+                let workbook_rels = part_rels(&mut xls, &main_part);
+                let find_part = |kind: &str, name: &str| {
+                    workbook_rels.iter()
+                        .find(|rel| rel.kind.ends_with(kind))
+                        .map(|rel| rel.target.clone())
+                        .unwrap_or_else(|| utils::resolve_target(&main_part, name))
+                };
+                let strings_part = find_part("/sharedStrings", "sharedStrings.xml");
+                let styles_part = find_part("/styles", "styles.xml");
+                timings.zip_open = watch.lap();
+                let strings = match strings {
+                    Some(strings) => strings,
+                    None => self::strings(&mut xls, &strings_part, decoder).into(),
+                };
+                timings.strings = watch.lap();
+                let styles = find_styles(&mut xls, &styles_part).into();
+                timings.styles = watch.lap();
+                let date_system = get_date_system(&mut workbook_reader);
+                timings.date_system = watch.lap();
+                if let Some(stats) = stats {
+                    if !supplied_strings {
+                        timings.strings_bytes = part_size(&mut xls, &strings_part);
+                    }
+                    timings.styles_bytes = part_size(&mut xls, &styles_part);
+                    *stats = timings;
+                }
                 Ok(Workbook {
                     path: path.to_string(),
                     xls,
-                    // encoding: String::from("utf8"),
+                    main_part,
+                    encoding,
                     date_system,
                     strings,
                     styles,
+                    decoder,
+                    forced_decoder,
+                    supplied_strings,
                 })
             },
-            Err(e) => Err(e.to_string())
+            Err(e) => {
+                // the most common reason for a file not being a zip is that it is an xls
+                if legacy_xls {
+                    Err(XlError::LegacyXls(path.to_owned()))
+                } else {
+                    Err(XlError::Open(e.to_string()))
+                }
+            },
         }
     }
 
     /// Alternative name for `Workbook::new`.
     pub fn open(path: &str) -> Result<Self, String> { Workbook::new(path) }
 
+    /// Like `open`, but you get an `XlError` back instead of its text if the workbook cannot be
+    /// opened, so you can tell the reasons apart (e.g., `XlError::LegacyXls` for an old .xls file
+    /// that could be converted first).
+    ///
+    /// # Example usage:
+    ///
+    ///     use xl::{Workbook, XlError};
+    ///
+    ///     assert!(Workbook::try_open("tests/data/Book1.xlsx").is_ok());
+    ///     match Workbook::try_open("tests/data/legacy.xls") {
+    ///         Err(XlError::LegacyXls(path)) => assert_eq!(path, "tests/data/legacy.xls"),
+    ///         _ => panic!("legacy.xls is an old-style workbook"),
+    ///     }
+    pub fn try_open(path: &str) -> Result<Self, XlError> {
+        Workbook::new_with_decoder(path, None, None, None)
+    }
+
+    /// Open the workbook's file again, e.g., after another program saved a new version of it.
+    /// The shared strings, styles, and date system are read again, but the way the workbook was
+    /// opened is kept (an encoding passed to `open_with_encoding` or strings passed to
+    /// `with_shared_strings` are still used). A `SheetMap` you got from `sheets` before reloading
+    /// may no longer match the file, so call `sheets` again afterwards.
+    ///
+    /// If the file cannot be opened anymore, you get an error back and the workbook is left as it
+    /// was.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     wb.reload().unwrap();
+    ///     assert_eq!(wb.sheets().len(), 4);
+    pub fn reload(&mut self) -> Result<(), XlError> {
+        if self.path.is_empty() {
+            return Err(XlError::Open("a workbook opened from a reader cannot be reloaded".to_owned()))
+        }
+        let strings = if self.supplied_strings { Some(Arc::clone(&self.strings)) } else { None };
+        *self = Workbook::new_with_decoder(&self.path, self.forced_decoder, strings, None)?;
+        Ok(())
+    }
+
     /// Simple method to print out all the inner files of the xlsx zip.
     pub fn contents(&mut self) {
+        for (i, part) in self.parts().iter().enumerate() {
+            if part.is_dir {
+                println!("File {}: \"{}\"", i, part.name);
+            } else {
+                println!("File {}: \"{}\" ({} bytes)", i, part.name, part.size);
+            }
+        }
+    }
+
+    /// List the inner files (parts) of the xlsx zip, e.g., to find out whether a workbook has any
+    /// drawings, charts, or custom parts.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let parts = wb.parts();
+    ///     assert!(parts.iter().any(|p| p.name == "xl/workbook.xml"));
+    pub fn parts(&mut self) -> Vec<PartInfo> {
+        let mut parts = Vec::with_capacity(self.xls.len());
         for i in 0 .. self.xls.len() {
-            let file = self.xls.by_index(i).unwrap();
-            let outpath = match file.enclosed_name() {
-                Some(path) => path.to_owned(),
+            // we only need the metadata, so don't ask zip to decompress (or decrypt) the entry;
+            // anything it still cannot read is left out of the list
+            let file = match self.xls.by_index_raw(i) {
+                Ok(file) => file,
+                Err(_) => continue,
+            };
+            let name = match file.enclosed_name() {
+                Some(path) => path.display().to_string(),
                 None => continue,
             };
+            parts.push(PartInfo { name, size: file.size(), is_dir: file.name().ends_with('/') });
+        }
+        parts
+    }
 
-            if (&*file.name()).ends_with('/') {
-                println!("File {}: \"{}\"", i, outpath.display());
-            } else {
-                println!(
-                    "File {}: \"{}\" ({} bytes)",
-                    i,
-                    outpath.display(),
-                    file.size()
-                );
+    /// Return the document properties of this workbook (author, creation date, title, and so
+    /// on), as recorded in "docProps/core.xml" and "docProps/app.xml". If those parts are
+    /// missing, you get back a `DocProperties` with every property set to `None`.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/properties.xlsx").unwrap();
+    ///     let props = wb.properties();
+    ///     assert_eq!(props.creator.as_deref(), Some("Kevin Ryan"));
+    ///     assert_eq!(props.application.as_deref(), Some("Microsoft Excel"));
+    pub fn properties(&mut self) -> DocProperties {
+        let mut props = DocProperties::default();
+        for part in &["docProps/core.xml", "docProps/app.xml"] {
+            if let Some(xml) = self.read_part(part) {
+                props.read(&xml);
+            }
+        }
+        props
+    }
+
+    /// Read the relationships of the part `owner` (e.g., "xl/worksheets/sheet1.xml" has its
+    /// relationships in "xl/worksheets/_rels/sheet1.xml.rels"). Targets are resolved to the names
+    /// of parts in the zip, except for relationships to things outside the workbook (e.g.,
+    /// hyperlinks), whose targets are left as they are. A part without relationships gives you an
+    /// empty list.
+    pub(crate) fn part_rels(&mut self, owner: &str) -> Vec<Relationship> {
+        part_rels(&mut self.xls, owner)
+    }
+
+    /// Return the relationships of `worksheet` (read from its own ".rels" part, e.g.,
+    /// "xl/worksheets/_rels/sheet1.xml.rels") as a map of relationship id to target. This is what
+    /// the `r:id` attributes in the worksheet xml (e.g., on a hyperlink or a drawing) point at.
+    /// Targets inside the workbook are resolved to the names of parts in the zip (e.g.,
+    /// "../drawings/drawing1.xml" becomes "xl/drawings/drawing1.xml"), while targets outside the
+    /// workbook (e.g., the address of a hyperlink) are left as they are. A worksheet without
+    /// relationships gives you an empty map.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/sheet_rels.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let rels = wb.sheet_rels(sheets.get("Sheet1").unwrap());
+    ///     assert_eq!(rels["rId1"], "xl/drawings/drawing1.xml");
+    ///     assert_eq!(rels["rId3"], "https://xlpro.tips/?a=1&b=2");
+    pub fn sheet_rels(&mut self, worksheet: &Worksheet) -> HashMap<String, String> {
+        self.part_rels(worksheet.target()).into_iter().map(|rel| (rel.id, rel.target)).collect()
+    }
+
+    /// The (uncompressed) bytes of the part `name`, or `None` if the zip does not have it.
+    pub(crate) fn read_part(&mut self, name: &str) -> Option<Vec<u8>> {
+        read_part(&mut self.xls, name)
+    }
+
+    /// Return the formula of the defined name `name` (e.g., "_xlnm.Print_Area") that is scoped to
+    /// the sheet at the 0-based `local_sheet_id`, or to the whole workbook if `local_sheet_id` is
+    /// `None`. Names are matched without regard to case, as they are in Excel.
+    pub(crate) fn defined_name(&mut self, name: &str, local_sheet_id: Option<usize>) -> Option<String> {
+        let main_part = self.main_part.clone();
+        let xml = self.read_part(&main_part)?;
+        let mut reader = Reader::from_reader(utils::skip_bom(&xml[..]));
+        reader.trim_text(true);
+        let mut buf = Vec::new();
+        let mut in_name = false;
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) if e.name() == b"definedName" => {
+                    let this_name = utils::get(e.attributes(), b"name").unwrap_or_default();
+                    let scope = utils::get(e.attributes(), b"localSheetId").and_then(|id| id.parse().ok());
+                    in_name = this_name.eq_ignore_ascii_case(name) && scope == local_sheet_id;
+                },
+                Ok(Event::Text(ref e)) if in_name => return Some(self.decoder.unescape(e.escaped())),
+                Ok(Event::End(ref e)) if e.name() == b"definedName" => in_name = false,
+                // the defined names come after the sheets, so there is nothing more to find
+                Ok(Event::End(ref e)) if e.name() == b"definedNames" => return None,
+                Ok(Event::Eof) | Err(_) => return None,
+                _ => (),
             }
+            buf.clear();
         }
     }
 
     /// Create a SheetReader for the given worksheet. A `SheetReader` is a struct in the
     /// `xl::Worksheet` class that can be used to iterate over rows, etc. See documentation in the
     /// `xl::Worksheet` module for more information.
-    pub fn sheet_reader<'a>(&'a mut self, zip_target: &str) -> SheetReader<'a> {
+    ///
+    /// If `zip_target` is not in the workbook (e.g., because a relationship points at a part that
+    /// does not exist), you get back an `XlError::MissingPart`.
+    pub fn sheet_reader<'a>(&'a mut self, zip_target: &str) -> Result<SheetReader<'a>, XlError> {
         let target = match self.xls.by_name(zip_target) {
             Ok(ws) => ws,
-            Err(_) => panic!("Could not find worksheet: {}", zip_target)
+            Err(_) => return Err(XlError::MissingPart(zip_target.to_owned())),
         };
         // let _ = std::io::copy(&mut target, &mut std::io::stdout());
+        let size = target.size();
         let reader = BufReader::new(target);
-        let mut reader = Reader::from_reader(reader);
+        let mut reader = Reader::from_reader(utils::skip_bom(reader));
         reader.trim_text(true);
-        SheetReader::new(reader, &self.strings, &self.styles, &self.date_system)
+        Ok(SheetReader::new(reader, &self.strings, &self.styles, &self.date_system)
+            .with_size(size)
+            .with_decoder(self.decoder))
+    }
+
+}
+
+
+/// The uncompressed size of the part `name` in `zip_file` (0 if it is not there)
+fn part_size<R: Read + Seek>(zip_file: &mut ZipArchive<R>, name: &str) -> u64 {
+    zip_file.by_name(name).map_or(0, |part| part.size())
+}
+
+/// The relationships of the part `owner` in `zip_file` (see `Workbook::part_rels`)
+fn part_rels<R: Read + Seek>(zip_file: &mut ZipArchive<R>, owner: &str) -> Vec<Relationship> {
+    let rels_path = match owner.rsplit_once('/') {
+        Some((folder, file)) => format!("{}/_rels/{}.rels", folder, file),
+        None => format!("_rels/{}.rels", owner),
+    };
+    let xml = match read_part(zip_file, &rels_path) {
+        Some(xml) => xml,
+        None => return vec![],
+    };
+    let mut reader = Reader::from_reader(utils::skip_bom(&xml[..]));
+    reader.trim_text(true);
+    let mut rels = Vec::new();
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Empty(ref e)) | Ok(Event::Start(ref e)) if e.local_name() == b"Relationship" => {
+                let mut rel = Relationship::default();
+                for a in e.attributes().flatten() {
+                    match a.key {
+                        b"Id" => rel.id = utils::attr_value(&a),
+                        b"Type" => rel.kind = utils::attr_value(&a),
+                        b"Target" => rel.target = TextDecoder::utf8().unescape(&a.value),
+                        b"TargetMode" => rel.external = &*a.value == b"External",
+                        _ => (),
+                    }
+                }
+                if !rel.external {
+                    rel.target = utils::resolve_target(owner, &rel.target);
+                }
+                rels.push(rel);
+            },
+            Ok(Event::Eof) | Err(_) => break,
+            _ => (),
+        }
+        buf.clear();
     }
+    rels
+}
 
+/// The (uncompressed) bytes of the part `name` in `zip_file` (see `Workbook::read_part`)
+fn read_part<R: Read + Seek>(zip_file: &mut ZipArchive<R>, name: &str) -> Option<Vec<u8>> {
+    // the size comes from the zip headers, which we do not trust with a huge allocation up front
+    // (the part still grows past this if it really is that big)
+    const MAX_RESERVE: u64 = 64 * 1024 * 1024;
+    let mut part = zip_file.by_name(name).ok()?;
+    let mut bytes = Vec::with_capacity(part.size().min(MAX_RESERVE) as usize);
+    part.read_to_end(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+/// The name of the main workbook part. This is almost always "xl/workbook.xml", but the package
+/// relationships ("_rels/.rels") are what actually say where it is.
+fn main_part<R: Read + Seek>(zip_file: &mut ZipArchive<R>) -> String {
+    part_rels(zip_file, "")
+        .into_iter()
+        .find(|rel| rel.kind.ends_with("/officeDocument"))
+        .map(|rel| rel.target)
+        .filter(|target| zip_file.by_name(target).is_ok())
+        .unwrap_or_else(|| "xl/workbook.xml".to_owned())
+}
+
+/// The content type `[Content_Types].xml` gives `part` (e.g., "xl/workbook.xml"): the one in its
+/// `Override` element if it has one, or the `Default` for its extension.
+fn content_type<R: Read + Seek>(zip_file: &mut ZipArchive<R>, part: &str) -> Option<String> {
+    let xml = read_part(zip_file, "[Content_Types].xml")?;
+    let mut reader = Reader::from_reader(utils::skip_bom(&xml[..]));
+    reader.trim_text(true);
+    let part_name = format!("/{}", part);
+    let extension = part.rsplit_once('.').map(|(_, ext)| ext);
+    let mut default = None;
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Empty(ref e)) | Ok(Event::Start(ref e)) => {
+                let content_type = utils::get(e.attributes(), b"ContentType");
+                // part names and extensions are compared without regard to case
+                match e.local_name() {
+                    b"Override" => {
+                        let name = utils::get(e.attributes(), b"PartName").unwrap_or_default();
+                        if name.eq_ignore_ascii_case(&part_name) {
+                            return content_type
+                        }
+                    },
+                    b"Default" => {
+                        let ext = utils::get(e.attributes(), b"Extension");
+                        if ext.is_some_and(|ext| extension.is_some_and(|e| e.eq_ignore_ascii_case(&ext))) {
+                            default = content_type;
+                        }
+                    },
+                    _ => (),
+                }
+            },
+            Ok(Event::Eof) | Err(_) => break,
+            _ => (),
+        }
+        buf.clear();
+    }
+    default
 }
 
+/// Could a main part with this content type be a workbook? Word documents and PowerPoint
+/// presentations (and other Office packages) declare main parts of their own kinds, but some
+/// programs that write xlsx files leave the workbook with a generic type (e.g.,
+/// "application/xml"), so only the main part of another kind of document counts against it.
+fn is_spreadsheet(content_type: &str) -> bool {
+    let main_part = content_type.ends_with(".main+xml");
+    !main_part || content_type.contains("spreadsheetml") || content_type.contains("ms-excel")
+}
 
-fn strings(zip_file: &mut ZipArchive<File>) -> Vec<String> {
+fn strings<R: Read + Seek>(zip_file: &mut ZipArchive<R>, part: &str, mut decoder: TextDecoder) -> Vec<String> {
     let mut strings = Vec::new();
-    match zip_file.by_name("xl/sharedStrings.xml") {
+    match zip_file.by_name(part) {
         Ok(strings_file) => {
             let reader = BufReader::new(strings_file);
-            let mut reader = Reader::from_reader(reader);
+            let mut reader = Reader::from_reader(utils::skip_bom(reader));
             reader.trim_text(true);
             let mut buf = Vec::new();
             let mut this_string = String::new();
@@ -386,16 +1154,13 @@ fn strings(zip_file: &mut ZipArchive<File>) -> Vec<String> {
                 match reader.read_event(&mut buf) {
                     Ok(Event::Start(ref e)) if e.name() == b"t" => {
                         if let Some(att) = utils::get(e.attributes(), b"xml:space") {
-                            if att == "preserve" {
-                                preserve_space = true;
-                            } else {
-                                preserve_space = false;
-                            }
+                            preserve_space = att == "preserve";
                         } else {
                             preserve_space = false;
                         }
                     },
-                    Ok(Event::Text(ref e)) => this_string.push_str(&e.unescape_and_decode(&reader).unwrap()[..]),
+                    Ok(Event::Decl(ref e)) => decoder.declared(e),
+                    Ok(Event::Text(ref e)) => this_string.push_str(&decoder.unescape(e.escaped())),
                     Ok(Event::Empty(ref e)) if e.name() == b"t" => strings.push("".to_owned()),
                     Ok(Event::End(ref e)) if e.name() == b"t" => {
                         if preserve_space {
@@ -420,16 +1185,16 @@ fn strings(zip_file: &mut ZipArchive<File>) -> Vec<String> {
 /// find the number of rows and columns used in a particular worksheet. takes the workbook xlsx
 /// location as its first parameter, and the location of the worksheet in question (within the zip)
 /// as the second parameter. Returns a tuple of (rows, columns) in the worksheet.
-fn find_styles(xlsx: &mut ZipArchive<fs::File>) -> Vec<String> {
+fn find_styles<R: Read + Seek>(xlsx: &mut ZipArchive<R>, part: &str) -> Vec<String> {
     let mut styles = Vec::new();
     let mut number_formats = standard_styles();
-    let styles_xml = match xlsx.by_name("xl/styles.xml") {
+    let styles_xml = match xlsx.by_name(part) {
         Ok(s) => s,
         Err(_) => return styles
     };
     // let _ = std::io::copy(&mut styles_xml, &mut std::io::stdout());
     let reader = BufReader::new(styles_xml);
-    let mut reader = Reader::from_reader(reader);
+    let mut reader = Reader::from_reader(utils::skip_bom(reader));
     reader.trim_text(true);
     let mut buf = Vec::new();
     let mut record_styles = false;
@@ -438,6 +1203,11 @@ fn find_styles(xlsx: &mut ZipArchive<fs::File>) -> Vec<String> {
             Ok(Event::Empty(ref e)) if e.name() == b"numFmt" => {
                 let id = utils::get(e.attributes(), b"numFmtId").unwrap();
                 let code = utils::get(e.attributes(), b"formatCode").unwrap();
+                // codes often quote literal text (e.g., &quot;$&quot;#,##0.00)
+                let code = match quick_xml::escape::unescape(code.as_bytes()) {
+                    Ok(unescaped) => String::from_utf8_lossy(&unescaped).into_owned(),
+                    Err(_) => code,
+                };
                 number_formats.insert(id, code);
             },
             Ok(Event::Start(ref e)) if e.name() == b"cellXfs" => {
@@ -502,34 +1272,35 @@ fn standard_styles() -> HashMap<String, String> {
     styles
 }
 
-fn get_date_system(xlsx: &mut ZipArchive<fs::File>) -> DateSystem {
-    match xlsx.by_name("xl/workbook.xml") {
-        Ok(wb) => {
-            let reader = BufReader::new(wb);
-            let mut reader = Reader::from_reader(reader);
-            reader.trim_text(true);
-            let mut buf = Vec::new();
-            loop {
-                match reader.read_event(&mut buf) {
-                    Ok(Event::Empty(ref e)) if e.name() == b"workbookPr" => {
-                        if let Some(system) = utils::get(e.attributes(), b"date1904") {
-                            if system == "1" {
-                                break DateSystem::V1904
-                            }
-                        }
-                        break DateSystem::V1900
-                    },
-                    Ok(Event::Eof) => break DateSystem::V1900,
-                    Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
-                    _ => (),
+/// Find the date system in the `workbookPr` element of the workbook xml, carrying on with the
+/// `reader` that `utils::declared_encoding` has already taken past the prolog.
+fn get_date_system(reader: &mut Reader<&[u8]>) -> DateSystem {
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Empty(ref e)) if e.name() == b"workbookPr" => {
+                if let Some(system) = utils::get(e.attributes(), b"date1904") {
+                    if system == "1" {
+                        break DateSystem::V1904
+                    }
                 }
-                buf.clear();
-            }
-        },
-        Err(_) => panic!("Could not find xl/workbook.xml")
+                break DateSystem::V1900
+            },
+            Ok(Event::Eof) | Err(_) => break DateSystem::V1900,
+            _ => (),
+        }
+        buf.clear();
     }
 }
 
+/// Does `source` start with the signature of an OLE compound file (which is how old .xls
+/// workbooks are stored)?
+fn is_legacy_xls(source: &mut dyn Source) -> bool {
+    const OLE_SIGNATURE: [u8; 8] = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+    let mut magic = [0; 8];
+    source.read_exact(&mut magic).is_ok() && magic == OLE_SIGNATURE
+}
+
 #[cfg(test)]
 mod tests {
     mod access {
@@ -541,6 +1312,54 @@ mod tests {
             assert!(wb.is_ok());
         }
 
+        #[test]
+        fn legacy_xls() {
+            assert!(matches!(Workbook::try_open("tests/data/legacy.xls"), Err(XlError::LegacyXls(_))));
+            assert!(matches!(Workbook::try_open("src/lib.rs"), Err(XlError::Open(_))));
+        }
+
+        #[test]
+        fn not_spreadsheets() {
+            for (path, kind) in &[("tests/data/document.docx", "wordprocessingml"), ("tests/data/slides.pptx", "presentationml")] {
+                match Workbook::try_open(path) {
+                    Err(XlError::NotSpreadsheet(p, content_type)) => {
+                        assert_eq!(&p, path);
+                        assert!(content_type.contains(kind), "{}", content_type);
+                    },
+                    _ => panic!("{} is not a spreadsheet", path),
+                }
+            }
+            let err = Workbook::open("tests/data/document.docx").err().unwrap();
+            assert!(err.starts_with("'tests/data/document.docx' is not a spreadsheet, but a Word document"), "{}", err);
+            assert!(is_spreadsheet("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"));
+            assert!(is_spreadsheet("application/vnd.ms-excel.sheet.macroEnabled.main+xml"));
+            assert!(is_spreadsheet("application/xml"));
+        }
+
+        #[test]
+        fn encoding() {
+            let wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+            assert_eq!(wb.encoding, "UTF-8");
+            let mut wb = Workbook::open("tests/data/encoding.xlsx").unwrap();
+            assert_eq!(wb.encoding, "windows-1252");
+            let sheets = wb.sheets();
+            let ws = sheets.get("Sheet1").unwrap();
+            assert_eq!(ws.encoding(&mut wb).unwrap(), "ISO-8859-1");
+        }
+
+        #[test]
+        fn sheet_start_tags() {
+            let mut wb = Workbook::open("tests/data/sheet_start_tags.xlsx").unwrap();
+            assert_eq!(wb.sheet_names(), vec!["First", "Second"]);
+            assert_eq!(wb.sheets().len(), 2);
+        }
+
+        #[test]
+        fn missing_workbook_part() {
+            let err = Workbook::open_with_stats("tests/data/no_workbook.xlsx").err().unwrap();
+            assert_eq!(err, XlError::MissingPart("xl/workbook.xml".to_owned()));
+        }
+
         #[test]
         fn all_sheets() {
             let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
@@ -555,6 +1374,223 @@ mod tests {
             assert!(sheets.get("Time").is_some());
         }
 
+        #[test]
+        fn zero_based_matches_one_based() {
+            let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+            let sheets = wb.sheets();
+            for (pos, ws) in sheets.iter() {
+                assert_eq!(sheets.get(pos).unwrap().name, ws.name);
+                assert_eq!(sheets.get_zero_based(pos - 1).unwrap().name, ws.name);
+            }
+            assert_eq!(sheets.iter().count(), 4);
+            assert!(sheets.get_zero_based(usize::MAX).is_none());
+        }
+
+        #[test]
+        fn worksheets_in_order() {
+            let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+            let sheets = wb.sheets();
+            let names: Vec<_> = sheets.worksheets().map(|ws| &ws.name[..]).collect();
+            assert_eq!(names, sheets.by_name());
+        }
+
+        #[test]
+        fn active_sheet() {
+            let mut wb = Workbook::open("tests/data/UPS.Galaxy.VS.PX.xlsx").unwrap();
+            assert_eq!(wb.sheets().active().map(|ws| ws.position), Some(2));
+            // activeTab points past the only sheet
+            let mut wb = Workbook::open("tests/data/inlinestrings.xlsx").unwrap();
+            assert!(wb.sheets().active().is_none());
+            let mut wb = Workbook::open("tests/data/frame.xlsx").unwrap();
+            assert!(wb.sheets().active().is_none());
+        }
+
+        #[test]
+        fn zip_variants() {
+            // entries stored without compression, and an archive that uses the ZIP64 records
+            // (64-bit sizes and offsets) throughout
+            for path in &["tests/data/stored.xlsx", "tests/data/zip64.xlsx"] {
+                let mut wb = Workbook::open(path).unwrap();
+                assert_eq!(wb.shared_strings(), ["alpha", "beta"]);
+                assert!(wb.parts().iter().any(|p| p.name == "xl/worksheets/sheet1.xml" && p.size > 0));
+                let sheets = wb.sheets();
+                let ws = sheets.get("Sheet1").unwrap();
+                let rows: Vec<String> = ws.rows(&mut wb).unwrap().map(|r| r.to_string()).collect();
+                assert_eq!(rows, ["\"alpha\",10", "\"beta\",20", "\"alpha\",30"], "{}", path);
+            }
+        }
+
+        #[test]
+        fn byte_order_marks() {
+            // every part (including the relationships) starts with a UTF-8 byte order mark
+            let mut wb = Workbook::open("tests/data/bom.xlsx").unwrap();
+            assert_eq!(wb.shared_strings(), ["Name", "Apple"]);
+            assert!(matches!(wb.date_system, DateSystem::V1904));
+            assert_eq!(wb.sheet_names(), ["Data"]);
+            let sheets = wb.sheets();
+            let ws = sheets.get("Data").unwrap();
+            let rows: Vec<String> = ws.rows(&mut wb).unwrap().map(|r| r.to_string()).collect();
+            assert_eq!(rows, ["\"Name\",2025-03-08", "\"Apple\",2.5"]);
+        }
+
+        #[test]
+        fn sheet_rels() {
+            let mut wb = Workbook::open("tests/data/sheet_rels.xlsx").unwrap();
+            let sheets = wb.sheets();
+            let rels = wb.sheet_rels(sheets.get(1).unwrap());
+            assert_eq!(rels.len(), 3);
+            // both the self-closing and the start/end form, with relative and absolute targets
+            assert_eq!(rels["rId1"], "xl/drawings/drawing1.xml");
+            assert_eq!(rels["rId2"], "xl/tables/table1.xml");
+            // external targets are kept as they are, apart from being unescaped
+            assert_eq!(rels["rId3"], "https://xlpro.tips/?a=1&b=2");
+            assert!(wb.sheet_rels(sheets.get(2).unwrap()).is_empty());
+        }
+
+        #[test]
+        fn sheet_states() {
+            let mut wb = Workbook::open("tests/data/sheet_states.xlsx").unwrap();
+            let sheets = wb.sheets();
+            let states: Vec<_> = sheets.worksheets().map(|ws| ws.state).collect();
+            assert_eq!(states, vec![SheetState::Visible, SheetState::Hidden, SheetState::VeryHidden]);
+            let ws = sheets.get(3).unwrap();
+            assert_eq!(ws.dimension(&mut wb), None);
+            assert_eq!(sheets.get(1).unwrap().dimension(&mut wb), Some("A1:B2".to_owned()));
+            assert_eq!(sheets.get_visible(1).unwrap().name, "Shown");
+            assert!(sheets.get_visible(0).is_none());
+            assert!(sheets.get_visible(2).is_none());
+        }
+
+        #[test]
+        fn loose_sheet_names() {
+            let mut wb = Workbook::open("tests/data/case_names.xlsx").unwrap();
+            let sheets = wb.sheets();
+            assert_eq!(sheets.get("other").unwrap().name, "Other");
+            assert_eq!(sheets.get("DATA").unwrap().position, 2);
+            assert_eq!(sheets.get("Data").unwrap().position, 1);
+            // "data" could be either of the first two sheets
+            assert!(sheets.get("data").is_none());
+            assert_eq!(sheets.get(" Other\t").unwrap().name, "Other");
+            assert_eq!(sheets.get("padded").unwrap().name, " Padded ");
+            assert_eq!(sheets.get(" Padded ").unwrap().position, 4);
+            // the spaces inside a name are part of it
+            assert!(sheets.get("Two Spaces").is_none());
+            assert_eq!(sheets.get(" two  spaces").unwrap().position, 5);
+        }
+
+        #[test]
+        fn sheet_kinds() {
+            let mut wb = Workbook::open("tests/data/chartsheet.xlsx").unwrap();
+            let sheets = wb.sheets();
+            let kinds: Vec<_> = sheets.worksheets().map(|ws| ws.kind()).collect();
+            assert_eq!(kinds, vec![SheetKind::Worksheet, SheetKind::Chartsheet, SheetKind::Dialogsheet]);
+            assert_eq!(sheets.get("Chart").unwrap().rows(&mut wb).unwrap().count(), 0);
+            assert_eq!(SheetKind::from_rel_type("http://schemas.microsoft.com/office/2006/relationships/xlMacrosheet"), SheetKind::Macrosheet);
+        }
+
+        #[test]
+        fn main_part_from_package_rels() {
+            let mut wb = Workbook::open("tests/data/custom_main_part.xlsx").unwrap();
+            assert_eq!(wb.main_part, "content/book.xml");
+            let sheets = wb.sheets();
+            let ws = sheets.get("Data").unwrap();
+            let row = ws.rows(&mut wb).unwrap().next().unwrap();
+            assert_eq!(row[0].value, crate::ExcelValue::String("hello".into()));
+            assert_eq!(row[1].value, crate::ExcelValue::Date(NaiveDate::from_ymd_opt(2018, 1, 1).unwrap()));
+        }
+
+        #[test]
+        fn open_from_reader() {
+            use std::io::Cursor;
+            let bytes = std::fs::read("tests/data/Book1.xlsx").unwrap();
+            let mut wb = Workbook::open_reader(Cursor::new(bytes)).unwrap();
+            assert_eq!(wb.path, "");
+            let sheets = wb.sheets();
+            let row10 = sheets.get("Sheet1").unwrap().rows(&mut wb).unwrap().nth(9).unwrap();
+            assert_eq!(row10[7].value, crate::ExcelValue::String("Test".into()));
+            assert!(matches!(wb.reload(), Err(XlError::Open(_))));
+            assert!(matches!(Workbook::open_reader(Cursor::new(b"not a zip".to_vec())), Err(XlError::Open(_))));
+            let legacy = std::fs::read("tests/data/legacy.xls").unwrap();
+            assert!(matches!(Workbook::open_reader(Cursor::new(legacy)), Err(XlError::LegacyXls(_))));
+        }
+
+        #[test]
+        fn open_stats() {
+            let (_, stats) = Workbook::open_with_stats("tests/data/formatted.xlsx").unwrap();
+            // formatted.xlsx has styles but no shared strings
+            assert_eq!(stats.strings_bytes, 0);
+            assert!(stats.styles_bytes > 0);
+            assert!(matches!(Workbook::open_with_stats("tests/data/missing.xlsx"), Err(XlError::Open(_))));
+        }
+
+        #[test]
+        fn reload_picks_up_changes() {
+            use std::fs;
+            let path = std::env::temp_dir().join(format!("xl-reload-{}.xlsx", std::process::id()));
+            let path = path.to_str().unwrap();
+            fs::copy("tests/data/Book1.xlsx", path).unwrap();
+            let mut wb = Workbook::open(path).unwrap();
+            assert_eq!(wb.sheets().len(), 4);
+            fs::copy("tests/data/frame.xlsx", path).unwrap();
+            wb.reload().unwrap();
+            assert_eq!(wb.sheet_names(), vec!["Sheet1"]);
+            fs::remove_file(path).unwrap();
+            assert!(matches!(wb.reload(), Err(XlError::Open(_))));
+            assert_eq!(wb.sheet_names(), vec!["Sheet1"]);
+        }
+
+        #[test]
+        fn parts() {
+            let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+            let parts = wb.parts();
+            let sheet = parts.iter().find(|p| p.name == "xl/worksheets/sheet1.xml").unwrap();
+            assert!(sheet.size > 0);
+            assert!(!sheet.is_dir);
+        }
+
+        #[test]
+        fn parts_with_unsupported_compression() {
+            let mut wb = Workbook::open("tests/data/unsupported_part.xlsx").unwrap();
+            let parts = wb.parts();
+            assert!(parts.iter().any(|p| p.name == "customXml/item1.xml"));
+            assert!(parts.iter().any(|p| p.name == "xl/worksheets/sheet1.xml"));
+        }
+
+        #[test]
+        fn properties() {
+            let mut wb = Workbook::open("tests/data/properties.xlsx").unwrap();
+            let props = wb.properties();
+            assert_eq!(props.created.as_deref(), Some("2021-03-07T15:04:05Z"));
+            assert_eq!(props.last_modified_by.as_deref(), Some("A & B"));
+            assert_eq!(props.title, None);
+            assert_eq!(props.company.as_deref(), Some("XL Pro Tips"));
+            let mut wb = Workbook::open("tests/data/errors.xlsx").unwrap();
+            assert_eq!(wb.properties(), DocProperties::default());
+        }
+
+        #[test]
+        fn supplied_shared_strings_are_used() {
+            let template = Workbook::open("tests/data/Book1.xlsx").unwrap();
+            let strings: Vec<_> = template.shared_strings().iter().map(|s| s.to_uppercase()).collect();
+            let mut wb = Workbook::with_shared_strings("tests/data/Book1.xlsx", strings).unwrap();
+            let sheets = wb.sheets();
+            let ws = sheets.get("Sheet1").unwrap();
+            let row10 = ws.rows(&mut wb).unwrap().nth(9).unwrap();
+            assert_eq!(row10[7].to_string(), "\"TEST\"");
+            let legacy = Workbook::with_shared_strings("tests/data/legacy.xls", template.shared_strings().to_vec());
+            assert!(matches!(legacy, Err(XlError::LegacyXls(_))));
+        }
+
+        #[test]
+        fn supplied_shared_strings_too_short() {
+            let mut wb = Workbook::with_shared_strings("tests/data/Book1.xlsx", Vec::new()).unwrap();
+            let sheets = wb.sheets();
+            let ws = sheets.get("Sheet1").unwrap();
+            let row10 = ws.rows(&mut wb).unwrap().nth(9).unwrap();
+            assert_eq!(row10[7].value, crate::ExcelValue::String(row10[7].raw_value.clone().into()));
+            assert_eq!(row10[7].shared_text, None);
+        }
+
         #[test]
         fn sheet_by_num_exists() {
             let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
@@ -563,6 +1599,7 @@ mod tests {
         }
 
         #[test]
+        #[allow(clippy::nonminimal_bool)]
         fn sheet_by_name_not_exists() {
             let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
             let sheets = wb.sheets();
@@ -570,6 +1607,7 @@ mod tests {
         }
 
         #[test]
+        #[allow(clippy::nonminimal_bool)]
         fn sheet_by_num_not_exists() {
             let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
             let sheets = wb.sheets();
@@ -584,11 +1622,22 @@ mod tests {
         }
 
         #[test]
+        fn workbook_is_send_and_sync() {
+            fn assert_send<T: Send>() {}
+            fn assert_sync<T: Sync>() {}
+            assert_send::<Workbook>();
+            assert_sync::<Workbook>();
+            assert_send::<SheetMap>();
+            assert_send::<Worksheet>();
+        }
+
+        #[test]
+        #[allow(clippy::iter_nth_zero)]
         fn inline_strings() {
             let mut wb = Workbook::open("tests/data/inlinestrings.xlsx").unwrap();
             let sheets = wb.sheets();
             let ws = sheets.get("Sheet Name").unwrap();
-            let row1 = ws.rows(&mut wb).nth(0).unwrap();
+            let row1 = ws.rows(&mut wb).unwrap().nth(0).unwrap();
             let v1 = &row1[0];
             assert_eq!(v1.to_string(), "\"Cell A1\"".to_string());
         }
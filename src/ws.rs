@@ -1,18 +1,23 @@
 //! This module implements all the functionality specific to Excel worksheets. This mostly means 
 
-use crate::utils;
+use crate::utils::{self, CellRef, RangeRef, TextDecoder};
 
 use std::borrow::Cow;
 use std::cmp;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::io::BufReader;
-use std::mem;
+use std::iter::FusedIterator;
 use std::ops::Index;
 use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
 use zip::read::ZipFile;
 use quick_xml::Reader;
-use quick_xml::events::Event;
+use quick_xml::events::{BytesStart, Event};
 // use quick_xml::events::attributes::Attribute;
+use crate::comments::{self, Comment};
+use crate::drawing::{self, Image};
+use crate::error::XlError;
+use crate::options::ReadOptions;
 use crate::wb::{DateSystem, Workbook};
 
 /// The `SheetReader` is used in a `RowIter` to navigate a worksheet. It contains a pointer to the
@@ -25,6 +30,9 @@ pub struct SheetReader<'a> {
     strings: &'a [String],
     styles: &'a [String],
     date_system: &'a DateSystem,
+    /// The size of the (decompressed) worksheet xml, if we know it
+    size: Option<u64>,
+    decoder: TextDecoder,
 }
 
 impl<'a> SheetReader<'a> {
@@ -48,34 +56,34 @@ impl<'a> SheetReader<'a> {
         strings: &'a [String],
         styles: &'a [String],
         date_system: &'a DateSystem) -> SheetReader<'a> {
-        SheetReader { reader, strings, styles, date_system }
+        SheetReader { reader, strings, styles, date_system, size: None, decoder: TextDecoder::utf8() }
+    }
+
+    /// Record the size of the decompressed worksheet xml (see `RowIter::total_bytes`).
+    pub(crate) fn with_size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Decode the text in the worksheet with `decoder` (see the `encoding` feature).
+    pub(crate) fn with_decoder(mut self, decoder: TextDecoder) -> Self {
+        self.decoder = decoder;
+        self
     }
 }
 
-/// find the number of rows and columns used in a particular worksheet. takes the workbook xlsx
-/// location as its first parameter, and the location of the worksheet in question (within the zip)
-/// as the second parameter. Returns a tuple of (rows, columns) in the worksheet.
+/// find the number of rows and columns used in a particular worksheet from the `ref` of its
+/// `dimension` element (e.g., "A1:C5"). Returns a tuple of (rows, columns) in the worksheet, or
+/// (0, 0) if `used_area_range` is not a range of cells.
 fn used_area(used_area_range: &str) -> (u32, u16) {
-    let mut end: isize = -1;
-    for (i, c) in used_area_range.chars().enumerate() {
-        if c == ':' { end = i as isize; break }
-    }
-    if end == -1 {
-        (0, 0)
-    } else {
-        let end_range = &used_area_range[end as usize..];
-        let mut end = 0;
-        // note, the extra '1' (in various spots below) is to deal with the ':' part of the
-        // range
-        for (i, c) in end_range[1..].chars().enumerate() {
-            if !c.is_ascii_alphabetic() {
-                end = i + 1;
-                break
-            }
-        }
-        let col = utils::col2num(&end_range[1..end]).unwrap();
-        let row: u32 = end_range[end..].parse().unwrap();
-        (row, col)
+    let end = match used_area_range.split_once(':') {
+        Some((_, end)) => end,
+        None => return (0, 0),
+    };
+    match utils::split_reference(end) {
+        // anything past the last column Excel allows is ignored anyway (see `RowIter`)
+        Some((col, row)) => (row, utils::col2num(col).unwrap_or(utils::XL_MAX_COL)),
+        None => (0, 0),
     }
 }
 
@@ -85,10 +93,80 @@ fn used_area(used_area_range: &str) -> (u32, u16) {
 pub struct Worksheet {
     pub name: String,
     pub position: u8,
+    /// Whether the worksheet is shown as a tab in Excel (see `SheetState`)
+    pub state: SheetState,
+    /// What kind of sheet this is (see `SheetKind`)
+    pub(crate) kind: SheetKind,
     /// location where we can find this worksheet in its xlsx file
     target: String,
 }
 
+/// Whether a worksheet is shown in Excel. Hidden worksheets can be unhidden from Excel's menus,
+/// while "very hidden" ones can only be unhidden from VBA. Either way, their data is still in the
+/// workbook and can be read like any other worksheet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SheetState {
+    Visible,
+    Hidden,
+    VeryHidden,
+}
+
+impl SheetState {
+    /// Parse the `state` attribute of a sheet in the workbook xml (anything we do not recognize
+    /// is visible, since that is the default).
+    pub(crate) fn from_attr(state: &str) -> Self {
+        match state {
+            "hidden" => SheetState::Hidden,
+            "veryHidden" => SheetState::VeryHidden,
+            _ => SheetState::Visible,
+        }
+    }
+
+    /// The name of the state as it is written in the workbook xml (e.g., "veryHidden")
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SheetState::Visible => "visible",
+            SheetState::Hidden => "hidden",
+            SheetState::VeryHidden => "veryHidden",
+        }
+    }
+}
+
+/// The kinds of sheet a workbook can hold. Only worksheets have rows and cells: a chart sheet is
+/// a tab that holds a single chart, and dialog sheets and macro sheets are left over from old
+/// versions of Excel. All of them are listed alongside each other in the workbook, so you get a
+/// `Worksheet` for each, but `rows` never finds anything in the ones that are not worksheets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SheetKind {
+    Worksheet,
+    Chartsheet,
+    Dialogsheet,
+    Macrosheet,
+}
+
+impl SheetKind {
+    /// The kind of sheet a relationship of type `rel_type` from the workbook points at (anything
+    /// we do not recognize is taken to be a worksheet)
+    pub(crate) fn from_rel_type(rel_type: &str) -> Self {
+        match rel_type.rsplit('/').next() {
+            Some("chartsheet") => SheetKind::Chartsheet,
+            Some("dialogsheet") => SheetKind::Dialogsheet,
+            Some("xlMacrosheet") | Some("xlIntlMacrosheet") => SheetKind::Macrosheet,
+            _ => SheetKind::Worksheet,
+        }
+    }
+
+    /// A lowercase name for this kind of sheet (e.g., "chartsheet")
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SheetKind::Worksheet => "worksheet",
+            SheetKind::Chartsheet => "chartsheet",
+            SheetKind::Dialogsheet => "dialogsheet",
+            SheetKind::Macrosheet => "macrosheet",
+        }
+    }
+}
+
 impl Worksheet {
     /// Create a new worksheet. Note that this method will probably not be called directly.
     /// Instead, you'll normally get a worksheet from a `Workbook` object. E.g.,:
@@ -100,7 +178,27 @@ impl Worksheet {
     ///     let ws = sheets.get("Time");
     ///     assert!(ws.is_some());
     pub fn new(name: String, position: u8, target: String) -> Self {
-        Worksheet { name, position, target, }
+        Worksheet { name, position, state: SheetState::Visible, kind: SheetKind::Worksheet, target, }
+    }
+
+    /// What kind of sheet this is. Everything in a workbook's list of tabs is a `Worksheet` to
+    /// us, but only `SheetKind::Worksheet` has rows to read (see `SheetMap::data_sheets` to skip
+    /// the others).
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{SheetKind, Workbook};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     assert_eq!(sheets.get("Time").unwrap().kind(), SheetKind::Worksheet);
+    pub fn kind(&self) -> SheetKind {
+        self.kind
+    }
+
+    /// The name of the part that holds this worksheet (e.g., "xl/worksheets/sheet1.xml")
+    pub(crate) fn target(&self) -> &str {
+        &self.target
     }
 
     /// Obtain a `RowIter` for this worksheet (that is in `workbook`). This is, arguably, the main
@@ -108,6 +206,9 @@ impl Worksheet {
     /// The simplest thing you can do is print the values out (which is what `xlcat` does), but you
     /// could do more if you wanted.
     ///
+    /// You get an `XlError` back if the part that holds the worksheet is missing from the
+    /// workbook. This only affects that one worksheet, so you can move on to the next one.
+    ///
     /// # Example usage
     ///
     ///     use xl::{Workbook, Worksheet, ExcelValue};
@@ -115,127 +216,1304 @@ impl Worksheet {
     ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
     ///     let sheets = wb.sheets();
     ///     let ws = sheets.get("Sheet1").unwrap();
-    ///     let mut rows = ws.rows(&mut wb);
+    ///     let mut rows = ws.rows(&mut wb).unwrap();
     ///     let row1 = rows.next().unwrap();
     ///     assert_eq!(row1[0].raw_value, "1");
     ///     assert_eq!(row1[1].value, ExcelValue::Number(2f64));
-    pub fn rows<'a>(&self, workbook: &'a mut Workbook) -> RowIter<'a> {
-        let reader = workbook.sheet_reader(&self.target);
-        RowIter {
+    pub fn rows<'a>(&self, workbook: &'a mut Workbook) -> Result<RowIter<'a>, XlError> {
+        let reader = workbook.sheet_reader(&self.target)?;
+        Ok(RowIter {
             worksheet_reader: reader,
             want_row: 1,
-            next_row: None,
+            pending: Vec::new(),
+            gap_checked: None,
+            column_filter: None,
+            filtered_out: HashSet::new(),
             num_cols: 0,
             num_rows: 0,
             done_file: false,
+            columns: None,
+            last_row: 0,
+            dimension_mismatch: None,
+            late_rows: Vec::new(),
+            shared_formulas: HashMap::new(),
+            first_row: 1,
+            final_row: None,
+            max_rows: None,
+            rows_returned: 0,
+            peeked: None,
+            pad_to_dimension: false,
+            trim_strings: false,
+            collapse_whitespace: false,
+            detect_error_text: false,
+            treat_leap_bug_as_number: false,
+            comma_decimal: false,
+            sparse: false,
+        })
+    }
+
+    /// Like `rows`, but only returns the part of the worksheet `opts` asks for (see
+    /// `ReadOptions`). You get an `XlError::BadOption` back if the options do not make sense
+    /// (e.g., a range like "C3:A1").
+    pub fn rows_with<'a>(&self, workbook: &'a mut Workbook, opts: &ReadOptions) -> Result<RowIter<'a>, XlError> {
+        let selection = opts.selection()?;
+        let mut rows = self.rows(workbook)?;
+        rows.columns = selection.columns;
+        rows.first_row = selection.first_row;
+        rows.final_row = selection.final_row;
+        rows.max_rows = selection.max_rows;
+        rows.trim_strings = selection.trim_strings;
+        rows.collapse_whitespace = selection.collapse_whitespace;
+        rows.detect_error_text = selection.detect_error_text;
+        rows.treat_leap_bug_as_number = selection.treat_leap_bug_as_number;
+        rows.comma_decimal = selection.comma_decimal;
+        Ok(rows)
+    }
+
+    /// Read this worksheet into a matrix of numbers, sized to its used area (one row of the matrix
+    /// per row of the worksheet, starting at row 1, and one column per column, starting at column
+    /// A). Numbers are copied as they are and dates and times become their serial numbers (in the
+    /// workbook's date system). Everything else (blanks, text, booleans, and errors) is NaN, so
+    /// use `f64::is_nan` to find the cells that did not hold a number.
+    ///
+    /// This is only available with the `ndarray` feature.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let array = ws.to_f64_array(&mut wb).unwrap();
+    ///     assert_eq!(array[[0, 1]], 2.0);
+    ///     assert!(array[[9, 7]].is_nan()); // "Test"
+    #[cfg(feature = "ndarray")]
+    pub fn to_f64_array(&self, workbook: &mut Workbook) -> Result<ndarray::Array2<f64>, XlError> {
+        let date_system = workbook.date_system;
+        let mut values = Vec::new();
+        let mut widths = Vec::new();
+        for row in self.rows(workbook)? {
+            widths.push(row.0.len());
+            values.extend(row.0.iter().map(|cell| match &cell.value {
+                ExcelValue::Number(n) => *n,
+                ExcelValue::Date(d) => utils::date_to_excel_number(d.and_hms_opt(0, 0, 0).unwrap(), &date_system),
+                ExcelValue::DateTime(d) => utils::date_to_excel_number(*d, &date_system),
+                ExcelValue::Time(t) => utils::time_to_excel_number(*t),
+                _ => f64::NAN,
+            }));
         }
+        let width = widths.iter().copied().max().unwrap_or(0);
+        let mut array = ndarray::Array2::from_elem((widths.len(), width), f64::NAN);
+        let mut values = values.into_iter();
+        for (i, row_width) in widths.into_iter().enumerate() {
+            for j in 0..row_width {
+                array[[i, j]] = values.next().unwrap();
+            }
+        }
+        Ok(array)
     }
 
-}
+    /// Read this worksheet into a polars `DataFrame`, using the first row for the column names
+    /// (blank names become the column letter, and repeated names get a "_2", "_3", ... suffix).
+    /// Unless `opts` says otherwise, each column gets the type all of its cells agree on: integers
+    /// (i64), other numbers (f64), booleans, or dates. Anything else is a text column. Blank cells
+    /// are nulls.
+    ///
+    /// This is only available with the `polars` feature.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{DataFrameOptions, Workbook};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/frame.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let df = ws.to_dataframe(&mut wb, &DataFrameOptions::new()).unwrap();
+    ///     assert_eq!(df.shape(), (3, 6));
+    #[cfg(feature = "polars")]
+    pub fn to_dataframe(
+        &self,
+        workbook: &mut Workbook,
+        opts: &crate::frame::DataFrameOptions,
+    ) -> Result<polars::prelude::DataFrame, XlError> {
+        crate::frame::to_dataframe(self, workbook, opts)
+    }
+
+    /// Return the images embedded in this worksheet, along with the cell each one is anchored to.
+    /// A worksheet without images (or whose drawing parts are missing) gives you an empty list.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/images.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let images = ws.images(&mut wb);
+    ///     assert_eq!(images[0].anchor.to_string(), "B3");
+    ///     assert_eq!(images[0].mime, "image/png");
+    pub fn images(&self, workbook: &mut Workbook) -> Vec<Image> {
+        drawing::images(workbook, &self.target)
+    }
+
+    /// Return the comments attached to the cells of this worksheet, ordered by row and then by
+    /// column. Both notes and the threaded comments of newer versions of Excel are included (see
+    /// `Comment::threaded`), and the replies to a threaded comment come back in its `replies`.
+    /// A worksheet without comments gives you an empty list.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/comments.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let comments = ws.comments(&mut wb);
+    ///     assert_eq!(comments[1].anchor.to_string(), "B2");
+    ///     assert_eq!(comments[1].author, "Alice Smith");
+    ///     assert_eq!(comments[1].replies[0].text, "Yes, checked it");
+    pub fn comments(&self, workbook: &mut Workbook) -> Vec<Comment> {
+        comments::comments(workbook, &self.target)
+    }
+
+    /// Return the text of each cell in the first row of this worksheet (handy for building a map
+    /// of column names to column numbers). Numbers and dates are converted to strings, and blank
+    /// cells come back as empty strings. Only the first row is read, so this is cheap even on a
+    /// huge worksheet.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let headers = ws.headers(&mut wb).unwrap();
+    ///     assert_eq!(headers[0], "1");
+    ///     assert_eq!(headers.len(), 18);
+    pub fn headers(&self, workbook: &mut Workbook) -> Result<Vec<String>, XlError> {
+        let row = match self.rows(workbook)?.next() {
+            Some(row) => row,
+            None => return Ok(vec![]),
+        };
+        Ok(row.0.iter().map(|cell| match &cell.value {
+            ExcelValue::String(s) => s.to_string(),
+            ExcelValue::Time(t) => t.to_string(),
+            value => value.to_string(),
+        }).collect())
+    }
+
+    /// Iterate through this worksheet one row at a time, with each cell as the text Excel would
+    /// show for it (see `Cell::formatted_value`): dates in their date format, numbers with their
+    /// grouping, currency, or percent signs, and so on. This is what you would get by copying the
+    /// worksheet out of Excel and pasting it as text. Blank cells are empty strings.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/formatted.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let row = ws.display_rows(&mut wb).unwrap().next().unwrap();
+    ///     assert_eq!(row, vec!["$1,234.50", "7-Mar-21", "12.50%", "(42)", "3.5"]);
+    pub fn display_rows<'a>(&self, workbook: &'a mut Workbook) -> Result<impl Iterator<Item = Vec<String>> + 'a, XlError> {
+        let rows = self.rows(workbook)?;
+        Ok(rows.map(|row| row.to_display_strings()))
+    }
+
+    /// Iterate over the cells of this worksheet that have a value, along with where each one is,
+    /// in the order they are in the worksheet. Unlike `rows`, blank cells are left out and empty
+    /// rows and cells are never "simulated", so this is the cheapest way to read a worksheet that
+    /// is mostly empty (e.g., into a sparse matrix).
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{CellRef, ExcelValue, Workbook};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/sparse.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let cells: Vec<_> = ws.cells(&mut wb).unwrap().collect();
+    ///     assert_eq!(cells.len(), 4);
+    ///     assert_eq!(cells[0], (CellRef::new(2, 2), ExcelValue::String("answer".into())));
+    ///     assert_eq!(cells[2], (CellRef::new(4, 5), ExcelValue::Number(3f64)));
+    pub fn cells<'a>(&self, workbook: &'a mut Workbook) -> Result<impl Iterator<Item = (CellRef, ExcelValue<'static>)> + 'a, XlError> {
+        let mut rows = self.rows(workbook)?;
+        rows.sparse = true;
+        Ok(rows.flat_map(|row| row.0.into_iter().filter(|cell| !cell.is_blank()).map(|cell| {
+            let (col, row) = cell.coordinates();
+            (CellRef::new(col, row), cell.value.into_owned())
+        })))
+    }
+
+    /// Return the values in one column of this worksheet (`col` is 1-based, so column C is 3),
+    /// from row 1 down to the last row of the worksheet. Blank cells are `ExcelValue::None`. Only
+    /// the one column is kept as the worksheet is read, so this is much cheaper than collecting
+    /// every row when you only need a single column.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{Workbook, ExcelValue};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let col = ws.column(&mut wb, 3).unwrap();
+    ///     assert_eq!(col[0], ExcelValue::Number(3f64));
+    ///     assert_eq!(col[1], ExcelValue::Number(21f64));
+    pub fn column(&self, workbook: &mut Workbook, col: u16) -> Result<Vec<ExcelValue<'static>>, XlError> {
+        let mut rows = self.rows(workbook)?;
+        rows.select_columns(&[col]);
+        Ok(rows.map(|row| match row.0.into_iter().next() {
+            Some(cell) => cell.value.into_owned(),
+            None => ExcelValue::None,
+        }).collect())
+    }
+
+    /// Return the values in `range` (e.g., "B2:D10", see `RangeRef`) as a matrix with one `Vec`
+    /// per row of the range and one value per column, so every row has the same length. Blank
+    /// cells (and cells past the end of the worksheet) are `ExcelValue::None`. Rows before the
+    /// range are skipped and we stop reading once we are past it. You get an `XlError::BadOption`
+    /// back if `range` is not a valid range.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{Workbook, ExcelValue};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let region = ws.region(&mut wb, "B1:C2").unwrap();
+    ///     assert_eq!(region, vec![
+    ///         vec![ExcelValue::Number(2f64), ExcelValue::Number(3f64)],
+    ///         vec![ExcelValue::Number(20f64), ExcelValue::Number(21f64)],
+    ///     ]);
+    pub fn region(&self, workbook: &mut Workbook, range: &str) -> Result<Vec<Vec<ExcelValue<'static>>>, XlError> {
+        let range: RangeRef = range.parse()?;
+        let mut region = vec![vec![ExcelValue::None; range.width()]; range.height()];
+        let mut rows = self.rows(workbook)?;
+        rows.select_columns(&(range.first_col..=range.last_col).collect::<Vec<_>>());
+        rows.first_row = range.first_row as usize;
+        rows.final_row = Some(range.last_row as usize);
+        for row in rows {
+            let values = match row.1.checked_sub(range.first_row as usize).and_then(|i| region.get_mut(i)) {
+                Some(values) => values,
+                None => continue,
+            };
+            for (value, cell) in values.iter_mut().zip(row.0) {
+                *value = cell.value.into_owned();
+            }
+        }
+        Ok(region)
+    }
+
+    /// Return true if no cell in this worksheet has a value (cells that only carry formatting do
+    /// not count). This stops reading as soon as it finds a value, so it is a cheap way to skip
+    /// the blank placeholder tabs some workbooks are padded with. Returns an `Err` if the worksheet
+    /// part is missing or its xml is malformed.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     assert!(!ws.is_empty(&mut wb).unwrap());
+    pub fn is_empty(&self, workbook: &mut Workbook) -> Result<bool, XlError> {
+        let mut reader = workbook.sheet_reader(&self.target)?.reader;
+        let mut buf = Vec::new();
+        let mut in_value = false;
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) if e.name() == b"v" || e.name() == b"t" => {
+                    in_value = true;
+                },
+                Ok(Event::Text(_)) if in_value => return Ok(false),
+                Ok(Event::End(ref e)) if e.name() == b"v" || e.name() == b"t" => {
+                    in_value = false;
+                },
+                Ok(Event::Eof) => return Ok(true),
+                Err(e) => return Err(XlError::Xml(reader.buffer_position(), e.to_string())),
+                _ => (),
+            }
+            buf.clear();
+        }
+    }
+
+    /// Return the color of this worksheet's tab, or `None` if the tab has no color (or the
+    /// worksheet part is missing). Colors are usually ARGB hex strings like "FFFF0000", but a tab
+    /// can also use a color from the workbook theme or from Excel's legacy palette, which you get
+    /// back as "theme:N" or "indexed:N".
+    ///
+    /// Only the start of the worksheet is read, so this is cheap even on a huge worksheet.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/tab_colors.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Red").unwrap();
+    ///     assert_eq!(ws.tab_color(&mut wb), Some("FFFF0000".to_owned()));
+    pub fn tab_color(&self, workbook: &mut Workbook) -> Option<String> {
+        let mut reader = workbook.sheet_reader(&self.target).ok()?.reader;
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.name() == b"tabColor" => {
+                    let mut color = None;
+                    for a in e.attributes().flatten() {
+                        match a.key {
+                            b"rgb" => return Some(utils::attr_value(&a)),
+                            b"theme" => color = Some(format!("theme:{}", utils::attr_value(&a))),
+                            b"indexed" => color = color.or_else(|| Some(format!("indexed:{}", utils::attr_value(&a)))),
+                            _ => (),
+                        }
+                    }
+                    return color
+                },
+                // the sheet properties always come before the cell data
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.name() == b"sheetData" => return None,
+                Ok(Event::Eof) | Err(_) => return None,
+                _ => (),
+            }
+            buf.clear();
+        }
+    }
+
+    /// Return the used area of this worksheet as recorded in its `dimension` element (e.g.,
+    /// "A1:C10"), or `None` if the worksheet does not record one (or its part is missing). Excel
+    /// writes "A1" for an empty worksheet. Not every program keeps the dimension up to date, so
+    /// treat it as a hint (see `RowIter::dimension_mismatch`).
+    ///
+    /// Only the start of the worksheet is read, so this is cheap even on a huge worksheet.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/dimension_overstated.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get(1).unwrap();
+    ///     assert_eq!(ws.dimension(&mut wb), Some("A1:B10".to_owned()));
+    pub fn dimension(&self, workbook: &mut Workbook) -> Option<String> {
+        let mut reader = workbook.sheet_reader(&self.target).ok()?.reader;
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.name() == b"dimension" => {
+                    return utils::get(e.attributes(), b"ref")
+                },
+                // the dimension always comes before the cell data
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.name() == b"sheetData" => return None,
+                Ok(Event::Eof) | Err(_) => return None,
+                _ => (),
+            }
+            buf.clear();
+        }
+    }
+
+    /// Return the print area of this worksheet as it is written in the workbook (e.g.,
+    /// "Sheet1!$A$1:$C$10"), or `None` if no print area is set. A print area can be made of
+    /// several ranges, which are separated by commas (e.g., "Sheet1!$A$1:$B$20,Sheet1!$D$1:$E$5").
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/print_area.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Other").unwrap();
+    ///     assert_eq!(ws.print_area(&mut wb), Some("Other!$A$1:$C$3".to_owned()));
+    pub fn print_area(&self, workbook: &mut Workbook) -> Option<String> {
+        // the print area is a defined name scoped to the sheet by its 0-based position
+        workbook.defined_name("_xlnm.Print_Area", Some((self.position as usize).checked_sub(1)?))
+    }
+
+    /// Return the data validations (e.g., dropdown lists) set up in this worksheet. Formulas are
+    /// returned as they are written in the xml, so a list of values looks like `"Yes,No"` (quotes
+    /// included) and a list that comes from somewhere else in the workbook looks like
+    /// `$D$1:$D$5` or `Choices`. Validations written by newer versions of Excel (the ones that
+    /// point at other worksheets) are included as well. A worksheet whose part is missing gives
+    /// you an empty list, and one with malformed xml gives you the validations before the problem.
+    ///
+    /// The validations come after the cell data in the worksheet xml, so this reads the whole
+    /// worksheet.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/validations.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let validations = ws.data_validations(&mut wb);
+    ///     assert_eq!(validations[0].kind, "list");
+    ///     assert_eq!(validations[0].sqref, vec!["B2:B100"]);
+    ///     assert_eq!(validations[0].formula1.as_deref(), Some("\"Yes,No\""));
+    pub fn data_validations(&self, workbook: &mut Workbook) -> Vec<DataValidation> {
+        let SheetReader { mut reader, mut decoder, .. } = match workbook.sheet_reader(&self.target) {
+            Ok(reader) => reader,
+            Err(_) => return vec![],
+        };
+        let mut validations = Vec::new();
+        let mut buf = Vec::new();
+        let mut current: Option<DataValidation> = None;
+        // which child of the current dataValidation we are reading text for
+        let mut field: Option<Vec<u8>> = None;
+        let mut text = String::new();
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) if e.local_name() == b"dataValidation" => {
+                    current = Some(DataValidation::from_attributes(e, &decoder));
+                },
+                Ok(Event::Empty(ref e)) if e.local_name() == b"dataValidation" => {
+                    validations.push(DataValidation::from_attributes(e, &decoder));
+                },
+                Ok(Event::Start(ref e)) if current.is_some() => {
+                    if let name @ b"formula1" | name @ b"formula2" | name @ b"sqref" = e.local_name() {
+                        field = Some(name.to_vec());
+                        text.clear();
+                    }
+                },
+                Ok(Event::Decl(ref e)) => decoder.declared(e),
+                Ok(Event::Text(ref e)) if field.is_some() => text.push_str(&decoder.unescape(e.escaped())),
+                Ok(Event::End(ref e)) if e.local_name() == b"dataValidation" => {
+                    if let Some(validation) = current.take() {
+                        validations.push(validation);
+                    }
+                },
+                Ok(Event::End(ref e)) if field.as_deref() == Some(e.local_name()) => {
+                    if let Some(validation) = current.as_mut() {
+                        match e.local_name() {
+                            b"formula1" => validation.formula1 = Some(text.clone()),
+                            b"formula2" => validation.formula2 = Some(text.clone()),
+                            _ => validation.sqref = text.split_whitespace().map(String::from).collect(),
+                        }
+                    }
+                    field = None;
+                },
+                Ok(Event::Eof) | Err(_) => break,
+                _ => (),
+            }
+            buf.clear();
+        }
+        validations
+    }
+
+    /// Return the encoding this worksheet's xml says it uses (e.g., "UTF-8"). See
+    /// `Workbook::encoding` for the encoding of the workbook as a whole.
+    pub fn encoding(&self, workbook: &mut Workbook) -> Result<String, XlError> {
+        let mut reader = workbook.sheet_reader(&self.target)?.reader;
+        Ok(utils::declared_encoding(&mut reader))
+    }
+
+    /// A lower-level alternative to `rows` for when you want to react to each cell as it is
+    /// parsed rather than work with whole rows. Your callback `f` receives a `CellEvent` for every
+    /// cell recorded in the worksheet xml (empty cells that Excel does not record are not
+    /// "simulated" like they are with `rows`). The `CellEvent` only borrows buffers that are
+    /// reused from one cell to the next, so this avoids nearly all of the allocations `rows`
+    /// makes (no `Row`s, `Cell`s, or `ExcelValue`s are created).
+    ///
+    /// Note that the raw value of a shared string cell (type "s") is its index in the workbook's
+    /// list of shared strings and not the string itself.
+    ///
+    /// If the xml is malformed, `f` has already seen the cells before the problem when you get the
+    /// `Err` back.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let mut total = 0f64;
+    ///     ws.for_each_cell(&mut wb, |cell| {
+    ///         if cell.reference == "A1" || cell.reference == "A2" {
+    ///             total += cell.raw_value.parse::<f64>().unwrap();
+    ///         }
+    ///     }).unwrap();
+    ///     assert_eq!(total, 20f64);
+    pub fn for_each_cell<F: FnMut(&CellEvent)>(&self, workbook: &mut Workbook, mut f: F) -> Result<(), XlError> {
+        let SheetReader { mut reader, mut decoder, .. } = workbook.sheet_reader(&self.target)?;
+        let mut buf = Vec::new();
+        let mut reference = String::new();
+        let mut cell_type = String::new();
+        let mut raw_value = String::new();
+        let mut in_value = false;
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) if e.name() == b"c" => {
+                    raw_value.clear();
+                    cell_event_attributes(e, &mut reference, &mut cell_type);
+                },
+                Ok(Event::Empty(ref e)) if e.name() == b"c" => {
+                    raw_value.clear();
+                    cell_event_attributes(e, &mut reference, &mut cell_type);
+                    f(&CellEvent { reference: &reference, cell_type: &cell_type, raw_value: &raw_value });
+                },
+                Ok(Event::Start(ref e)) if e.name() == b"v" || e.name() == b"t" => {
+                    in_value = true;
+                },
+                Ok(Event::Decl(ref e)) => decoder.declared(e),
+                Ok(Event::Text(ref e)) if in_value => {
+                    raw_value.push_str(&decoder.unescape(e.escaped()));
+                },
+                Ok(Event::End(ref e)) if e.name() == b"v" || e.name() == b"t" => {
+                    in_value = false;
+                },
+                Ok(Event::End(ref e)) if e.name() == b"c" => {
+                    f(&CellEvent { reference: &reference, cell_type: &cell_type, raw_value: &raw_value });
+                },
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(XlError::Xml(reader.buffer_position(), e.to_string())),
+                _ => (),
+            }
+            buf.clear();
+        }
+        Ok(())
+    }
 
-/// `ExcelValue` is the enum that holds the equivalent "rust value" of a `Cell`s "raw_value."
-#[derive(Debug, PartialEq)]
-pub enum ExcelValue<'a> {
-    Bool(bool),
-    Date(NaiveDate),
-    DateTime(NaiveDateTime),
-    Error(String),
-    None,
-    Number(f64),
-    String(Cow<'a, str>),
-    Time(NaiveTime),
 }
 
-impl fmt::Display for ExcelValue<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            ExcelValue::Bool(b) => write!(f, "{}", b),
-            ExcelValue::Date(d) => write!(f, "{}", d),
-            ExcelValue::DateTime(d) => write!(f, "{}", d),
-            ExcelValue::Error(e) => write!(f, "#{}", e),
-            ExcelValue::None => write!(f, ""),
-            ExcelValue::Number(n) => write!(f, "{}", n),
-            ExcelValue::String(s) => write!(f, "\"{}\"", s.replace(r#"""#, r#""""#)),
-            ExcelValue::Time(t) => write!(f, "\"{}\"", t),
+/// Fill `reference` and `cell_type` from the attributes of a `c` element, reusing their buffers.
+fn cell_event_attributes(e: &BytesStart, reference: &mut String, cell_type: &mut String) {
+    reference.clear();
+    cell_type.clear();
+    for a in e.attributes() {
+        let a = a.unwrap();
+        if a.key == b"r" {
+            reference.push_str(&String::from_utf8_lossy(&a.value));
+        }
+        if a.key == b"t" {
+            cell_type.push_str(&String::from_utf8_lossy(&a.value));
         }
     }
 }
 
+/// A `CellEvent` is what you receive in the callback you pass to `Worksheet::for_each_cell`. All
+/// of the fields are borrowed from buffers owned by the parser, so copy anything you need to keep
+/// around after the callback returns.
 #[derive(Debug)]
-pub struct Cell<'a> {
-    /// The value you get by converting the raw_value (a string) into a Rust value
-    pub value: ExcelValue<'a>,
-    /// The formula (may be "empty") of the cell
-    pub formula: String,
+pub struct CellEvent<'a> {
     /// What cell are we looking at? E.g., B3, A1, etc.
-    pub reference: String,
-    /// The cell style (e.g., the style you see in Excel by hitting Ctrl+1 and going to the
-    /// "Number" tab).
-    pub style: String,
+    pub reference: &'a str,
     /// The type of cell as recorded by Excel (s = string using sharedStrings.xml, str = raw
-    /// string, b = boolean, etc.). This may change from a `String` type to an `Enum` of some sorts
-    /// in the future.
-    pub cell_type: String,
+    /// string, b = boolean, etc.). Empty for numbers and dates.
+    pub cell_type: &'a str,
     /// The raw string value recorded in the xml
-    pub raw_value: String,
+    pub raw_value: &'a str,
 }
 
-impl Cell<'_> {
-    /// return the row/column coordinates of the current cell
-    pub fn coordinates(&self) -> (u16, u32) {
-        // let (col, row) = split_cell_reference(&self.reference);
-        let (col, row) = {
-            let r = &self.reference;
-            let mut end = 0;
-            for (i, c) in r.chars().enumerate() {
-                if !c.is_ascii_alphabetic() {
-                    end = i;
-                    break
-                }
+/// A data validation rule from a worksheet (see `Worksheet::data_validations`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DataValidation {
+    /// The type of validation: "list", "whole", "decimal", "date", "time", "textLength",
+    /// "custom", or "none"
+    pub kind: String,
+    /// The ranges the validation applies to (e.g., `["B2:B100", "D2"]`)
+    pub sqref: Vec<String>,
+    /// The first formula of the rule (for a list, the allowed values)
+    pub formula1: Option<String>,
+    /// The second formula of the rule (e.g., the upper bound of a "between" rule)
+    pub formula2: Option<String>,
+}
+
+impl DataValidation {
+    fn from_attributes(e: &BytesStart, decoder: &TextDecoder) -> Self {
+        let mut validation = DataValidation { kind: "none".to_owned(), ..Default::default() };
+        for a in e.attributes().flatten() {
+            match a.key {
+                b"type" => validation.kind = decoder.unescape(&a.value),
+                b"sqref" => {
+                    validation.sqref = decoder.unescape(&a.value).split_whitespace().map(String::from).collect()
+                },
+                _ => (),
             }
-            (&r[..end], &r[end..])
-        };
-        let col = utils::col2num(col).unwrap();
-        let row = row.parse().unwrap();
-        (col, row)
+        }
+        validation
     }
 }
 
-#[derive(Debug)]
-pub struct Row<'a>(pub Vec<Cell<'a>>, pub usize);
+/// The error values a cell can hold (these are also the error literals you can type into a
+/// formula). Anything that is not one of Excel's own error values ends up in `Other`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CellError {
+    /// #NULL!
+    Null,
+    /// #DIV/0!
+    Div0,
+    /// #VALUE!
+    Value,
+    /// #REF!
+    Ref,
+    /// #NAME?
+    Name,
+    /// #NUM!
+    Num,
+    /// #N/A
+    NA,
+    /// #GETTING_DATA
+    GettingData,
+    /// #SPILL!
+    Spill,
+    /// #CALC!
+    Calc,
+    /// Some other error (e.g., a date that cannot exist)
+    Other(String),
+}
 
-impl<'a> Index<u16> for Row<'a> {
-    type Output = Cell<'a>;
+impl CellError {
+    /// Every error Excel itself produces (i.e., everything but `Other`)
+    pub(crate) const CANONICAL: [CellError; 10] = [
+        CellError::Null,
+        CellError::Div0,
+        CellError::Value,
+        CellError::Ref,
+        CellError::Name,
+        CellError::Num,
+        CellError::NA,
+        CellError::GettingData,
+        CellError::Spill,
+        CellError::Calc,
+    ];
 
-    fn index(&self, column_index: u16) -> &Self::Output {
-        &self.0[column_index as usize]
+    /// Is `text` exactly one of the errors Excel produces (e.g., "#N/A")?
+    pub(crate) fn is_canonical(text: &str) -> bool {
+        CellError::CANONICAL.iter().any(|e| e.as_str() == text)
     }
-}
 
-impl fmt::Display for Row<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let vec = &self.0;
-        for (count, v) in vec.iter().enumerate() {
-            if count != 0 { write!(f, ",")?; }
-            write!(f, "{}", v)?;
+    /// The text Excel uses for this error (e.g., "#DIV/0!")
+    pub fn as_str(&self) -> &str {
+        match self {
+            CellError::Null => "#NULL!",
+            CellError::Div0 => "#DIV/0!",
+            CellError::Value => "#VALUE!",
+            CellError::Ref => "#REF!",
+            CellError::Name => "#NAME?",
+            CellError::Num => "#NUM!",
+            CellError::NA => "#N/A",
+            CellError::GettingData => "#GETTING_DATA",
+            CellError::Spill => "#SPILL!",
+            CellError::Calc => "#CALC!",
+            CellError::Other(e) => e,
         }
-        write!(f, "")
     }
 }
 
-impl fmt::Display for Cell<'_> {
+/// Excel writes errors with their leading "#" (e.g., "#DIV/0!"), but other programs sometimes
+/// leave it out (or double it up), so we read the error whichever way it is written. An error we
+/// do not know about keeps its text, with exactly one "#" in front (e.g., "#BOGUS!").
+impl From<&str> for CellError {
+    fn from(error: &str) -> Self {
+        let name = error.trim_start_matches('#');
+        CellError::CANONICAL.iter()
+            .find(|e| &e.as_str()[1..] == name)
+            .cloned()
+            .unwrap_or_else(|| CellError::Other(if name.is_empty() { String::new() } else { format!("#{}", name) }))
+    }
+}
+
+impl fmt::Display for CellError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.value)
+        write!(f, "{}", self.as_str())
     }
 }
 
-pub struct RowIter<'a> {
+/// The type of a cell, as recorded in the `t` attribute of its xml. The type says how to read the
+/// cell's raw value, so it is what decides which `ExcelValue` the cell ends up with.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum CellType {
+    /// "s": an index into the workbook's shared strings
+    Shared,
+    /// "inlineStr": text stored in the cell itself
+    InlineStr,
+    /// "str": the text result of a formula
+    Str,
+    /// "b": a boolean
+    Bool,
+    /// "e": an error (e.g., #N/A)
+    Error,
+    /// "n" (or no type at all): a number, which may be a date depending on the cell's style
+    #[default]
+    Number,
+    /// "d": a date in ISO 8601 form (e.g., "2021-03-07T15:04:05")
+    Date,
+    /// A type that is not in the spec (we read these as numbers, or as text if they are not one)
+    Other(String),
+}
+
+impl CellType {
+    /// The type as it is written in the xml (e.g., "inlineStr")
+    pub fn as_str(&self) -> &str {
+        match self {
+            CellType::Shared => "s",
+            CellType::InlineStr => "inlineStr",
+            CellType::Str => "str",
+            CellType::Bool => "b",
+            CellType::Error => "e",
+            CellType::Number => "n",
+            CellType::Date => "d",
+            CellType::Other(t) => t,
+        }
+    }
+}
+
+impl From<&str> for CellType {
+    fn from(t: &str) -> Self {
+        match t {
+            "s" => CellType::Shared,
+            "inlineStr" => CellType::InlineStr,
+            "str" => CellType::Str,
+            "b" => CellType::Bool,
+            "e" => CellType::Error,
+            "n" | "" => CellType::Number,
+            "d" => CellType::Date,
+            t => CellType::Other(t.to_owned()),
+        }
+    }
+}
+
+impl std::str::FromStr for CellType {
+    /// Every string is a cell type (types we do not know become `CellType::Other`)
+    type Err = std::convert::Infallible;
+
+    /// Parse a type as it is written in the xml. For example:
+    ///
+    ///     use xl::CellType;
+    ///
+    ///     assert_eq!("inlineStr".parse::<CellType>().unwrap(), CellType::InlineStr);
+    ///     assert_eq!("".parse::<CellType>().unwrap(), CellType::Number);
+    ///     assert_eq!("inlineStr".parse::<CellType>().unwrap().to_string(), "inlineStr");
+    fn from_str(t: &str) -> Result<Self, Self::Err> {
+        Ok(CellType::from(t))
+    }
+}
+
+impl fmt::Display for CellType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// `ExcelValue` is the enum that holds the equivalent "rust value" of a `Cell`s "raw_value."
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExcelValue<'a> {
+    Bool(bool),
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+    Error(CellError),
+    None,
+    Number(f64),
+    String(Cow<'a, str>),
+    Time(NaiveTime),
+}
+
+impl ExcelValue<'_> {
+    /// Is this a number without a fractional part (e.g., 3 but not 3.5)? Excel stores every
+    /// number as an f64, so this is the best way to tell whether a column holds whole numbers.
+    /// Dates and times are not numbers here, even though Excel stores them as serial numbers.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::ExcelValue;
+    ///
+    ///     assert!(ExcelValue::Number(3.0).is_integer());
+    ///     assert!(!ExcelValue::Number(3.5).is_integer());
+    ///     assert!(!ExcelValue::Bool(true).is_integer());
+    pub fn is_integer(&self) -> bool {
+        match self {
+            ExcelValue::Number(n) => n.is_finite() && n.fract() == 0.0,
+            _ => false,
+        }
+    }
+
+    /// Make a copy of this value that does not borrow anything, so it can outlive the workbook it
+    /// was read from.
+    pub fn into_owned(self) -> ExcelValue<'static> {
+        match self {
+            ExcelValue::Bool(b) => ExcelValue::Bool(b),
+            ExcelValue::Date(d) => ExcelValue::Date(d),
+            ExcelValue::DateTime(d) => ExcelValue::DateTime(d),
+            ExcelValue::Error(e) => ExcelValue::Error(e),
+            ExcelValue::None => ExcelValue::None,
+            ExcelValue::Number(n) => ExcelValue::Number(n),
+            ExcelValue::String(s) => ExcelValue::String(Cow::Owned(s.into_owned())),
+            ExcelValue::Time(t) => ExcelValue::Time(t),
+        }
+    }
+}
+
+impl fmt::Display for ExcelValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExcelValue::Bool(b) => write!(f, "{}", b),
+            ExcelValue::Date(d) => write!(f, "{}", d),
+            ExcelValue::DateTime(d) => write!(f, "{}", d),
+            ExcelValue::Error(e) => write!(f, "{}", e),
+            ExcelValue::None => write!(f, ""),
+            ExcelValue::Number(n) => write!(f, "{}", n),
+            ExcelValue::String(s) => write!(f, "\"{}\"", s.replace(r#"""#, r#""""#)),
+            ExcelValue::Time(t) => write!(f, "\"{}\"", t),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Cell<'a> {
+    /// The value you get by converting the raw_value (a string) into a Rust value
+    pub value: ExcelValue<'a>,
+    /// The formula (may be "empty") of the cell. When a formula is shared by several cells (Excel
+    /// only records it once, in the first cell that uses it), we rebuild each cell's own formula by
+    /// adjusting the relative references in the shared formula (see `xl::shift_formula`).
+    pub formula: String,
+    /// What cell are we looking at? E.g., B3, A1, etc.
+    pub reference: String,
+    /// The cell style (e.g., the style you see in Excel by hitting Ctrl+1 and going to the
+    /// "Number" tab).
+    pub style: String,
+    /// The type of cell as recorded by Excel (see `CellType`)
+    pub cell_type: CellType,
+    /// The raw string value recorded in the xml. For a shared string cell (type "s"), this is
+    /// the index of the string in the workbook's shared strings rather than the text itself; use
+    /// `raw_text` if you want the text.
+    pub raw_value: String,
+    /// For a shared string cell, its text exactly as it is in the workbook's shared strings
+    /// (borrowed from them, like `value`), and `None` for any other cell
+    pub shared_text: Option<&'a str>,
+}
+
+impl Default for Cell<'_> {
+    /// A blank cell, without a reference, style, or formula
+    fn default() -> Self {
+        new_cell()
+    }
+}
+
+impl Cell<'_> {
+    /// The text Excel stored for this cell, before it is converted into `value`: the same as
+    /// `raw_value`, except that for a shared string cell you get the string itself instead of its
+    /// index. Unlike `value`, this is never changed by the `ReadOptions` (e.g., trimming), and
+    /// numbers are exactly as written (e.g., "0.30000000000000004").
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let row10 = ws.rows(&mut wb).unwrap().nth(9).unwrap();
+    ///     assert_eq!(row10[0].raw_text(), "163");
+    ///     assert_eq!(row10[7].raw_text(), "Test");
+    ///     assert_ne!(row10[7].raw_value, "Test");
+    pub fn raw_text(&self) -> &str {
+        self.shared_text.unwrap_or(&self.raw_value)
+    }
+
+    /// return the row/column coordinates of the current cell. Every cell read from a worksheet
+    /// has a valid reference, but this panics for one that does not (e.g., `Cell::default()`).
+    pub fn coordinates(&self) -> (u16, u32) {
+        coordinates(&self.reference)
+    }
+
+    /// Does this cell hold a date (with or without a time of day)?
+    pub fn is_date(&self) -> bool {
+        matches!(self.value, ExcelValue::Date(_) | ExcelValue::DateTime(_))
+    }
+
+    /// Does this cell hold a time of day (without a date)?
+    pub fn is_time(&self) -> bool {
+        matches!(self.value, ExcelValue::Time(_))
+    }
+
+    /// Does this cell hold a number (that is not formatted as a date or time)?
+    pub fn is_number(&self) -> bool {
+        matches!(self.value, ExcelValue::Number(_))
+    }
+
+    /// Does this cell hold text?
+    pub fn is_string(&self) -> bool {
+        matches!(self.value, ExcelValue::String(_))
+    }
+
+    /// Does this cell hold TRUE or FALSE?
+    pub fn is_bool(&self) -> bool {
+        matches!(self.value, ExcelValue::Bool(_))
+    }
+
+    /// Does this cell hold an error (e.g., #N/A)?
+    pub fn is_error(&self) -> bool {
+        matches!(self.value, ExcelValue::Error(_))
+    }
+
+    /// Is this cell empty?
+    pub fn is_blank(&self) -> bool {
+        matches!(self.value, ExcelValue::None)
+    }
+
+    /// The value of this cell as an integer, if it holds a whole number that fits in an i64 (see
+    /// `ExcelValue::is_integer`).
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.value {
+            // i64::MAX as f64 rounds up to 2^63, which is already out of range
+            ExcelValue::Number(n) if self.value.is_integer() && n >= i64::MIN as f64 && n < i64::MAX as f64 => {
+                Some(n as i64)
+            },
+            _ => None,
+        }
+    }
+
+    /// The value of this cell as Excel would show it, i.e., formatted with the cell's number
+    /// format (a date with a "d-mmm-yy" format becomes "7-Mar-21", a number with a "$#,##0.00"
+    /// format becomes "$1,234.50", and so on). See `ExcelValue::format` for the details.
+    pub fn formatted_value(&self) -> String {
+        let code = if self.style.is_empty() { "General" } else { &self.style };
+        self.value.format(code)
+    }
+}
+
+/// Quote `text` for CSV if it needs it (i.e., if it has a comma, quote, or line break in it)
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_owned()
+    }
+}
+
+/// return the row/column coordinates of a cell reference like "B3"
+fn coordinates(reference: &str) -> (u16, u32) {
+    try_coordinates(reference).unwrap()
+}
+
+/// return the row/column coordinates of a cell reference like "B3", or `None` if the reference is
+/// not a valid cell (e.g., "XFE1", which is past the last column Excel allows)
+fn try_coordinates(reference: &str) -> Option<(u16, u32)> {
+    let (col, row) = utils::split_reference(reference)?;
+    Some((utils::col2num(col)?, row))
+}
+
+#[derive(Debug)]
+pub struct Row<'a>(pub Vec<Cell<'a>>, pub usize);
+
+impl<'a> Index<u16> for Row<'a> {
+    type Output = Cell<'a>;
+
+    fn index(&self, column_index: u16) -> &Self::Output {
+        &self.0[column_index as usize]
+    }
+}
+
+impl Row<'_> {
+    /// Format this row as a line of comma-separated values, the same way `to_string` does, except
+    /// that blank cells are written as `na` instead of being left empty (e.g., "NA" or "NaN" for
+    /// tools that want something in every field). `na` is quoted if it has a comma, quote, or line
+    /// break in it.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Time").unwrap();
+    ///     let row = ws.rows(&mut wb).unwrap().nth(1).unwrap();
+    ///     assert_eq!(row.to_string(), "");
+    ///     assert_eq!(row.to_csv("NA"), "NA");
+    ///     assert_eq!(row.to_csv("n/a, blank"), "\"n/a, blank\"");
+    pub fn to_csv(&self, na: &str) -> String {
+        let na = csv_field(na);
+        let cells: Vec<String> = self.0.iter().map(|cell| match cell.value {
+            ExcelValue::None => na.clone(),
+            _ => cell.to_string(),
+        }).collect();
+        cells.join(",")
+    }
+
+    /// The cell in column `col` (e.g., "C"), or `None` if the row does not have one (e.g., a
+    /// column you did not select, or one past the end of the row). Unlike indexing (`row[2]`),
+    /// this finds the cell by its reference, so you get the right cell even when the row only
+    /// holds some of its columns (see `RowIter::select_columns`).
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{ExcelValue, Workbook};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let mut rows = ws.rows(&mut wb).unwrap();
+    ///     rows.select_columns(&[1, 8]);
+    ///     let row10 = rows.nth(9).unwrap();
+    ///     assert_eq!(row10.cell("H").unwrap().value, ExcelValue::String("Test".into()));
+    ///     assert_eq!(row10.cell("h").unwrap().reference, "H10");
+    ///     assert!(row10.cell("B").is_none());
+    pub fn cell(&self, col: &str) -> Option<&Cell<'_>> {
+        let col = utils::col2num(&col.to_uppercase())?;
+        let matches = |cell: &&Cell| try_coordinates(&cell.reference).is_some_and(|(c, _)| c == col);
+        // cells are usually where their column says they are
+        match self.0.get(col as usize - 1).filter(matches) {
+            Some(cell) => Some(cell),
+            None => self.0.iter().find(matches),
+        }
+    }
+
+    /// The text Excel would show for each cell in this row, formatted with the cell's number
+    /// format (see `Cell::formatted_value`). Blank cells are empty strings.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/formatted.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let row = ws.rows(&mut wb).unwrap().next().unwrap();
+    ///     assert_eq!(row.to_display_strings(), vec!["$1,234.50", "7-Mar-21", "12.50%", "(42)", "3.5"]);
+    ///     assert_eq!(row.to_raw_strings(), vec!["1234.5", "44262", "0.125", "-42", "3.5"]);
+    pub fn to_display_strings(&self) -> Vec<String> {
+        self.0.iter().map(Cell::formatted_value).collect()
+    }
+
+    /// The text Excel stored for each cell in this row, without any formatting (see
+    /// `Cell::raw_text`): numbers exactly as written, dates as their serial numbers, booleans as
+    /// "1" or "0", and shared strings as their text. Blank cells are empty strings.
+    pub fn to_raw_strings(&self) -> Vec<String> {
+        self.0.iter().map(|cell| cell.raw_text().to_owned()).collect()
+    }
+}
+
+impl fmt::Display for Row<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let vec = &self.0;
+        for (count, v) in vec.iter().enumerate() {
+            if count != 0 { write!(f, ",")?; }
+            write!(f, "{}", v)?;
+        }
+        write!(f, "")
+    }
+}
+
+impl fmt::Display for Cell<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+/// An iterator over the rows of a worksheet (see `Worksheet::rows` and `Worksheet::rows_with`).
+///
+/// Rows are returned in order, with an empty row for each row the worksheet xml leaves out. A
+/// few programs write rows out of order (e.g., row 5 before rows 3 and 4). When that happens
+/// right after a gap, we read ahead until the rows are back in order and return them sorted. A
+/// row that turns up much later than it should (after we have returned an empty row in its spot,
+/// or another row with the same number) is left out, so the row numbers (`row.1`) always go up;
+/// `late_rows` tells you which rows that happened to.
+///
+/// A `RowIter` cannot be rewound. The worksheet xml is decompressed as it is read, and a zip entry
+/// can only be read from the start, so going back means opening the entry again. For a second
+/// pass over the same worksheet (e.g., to infer the type of each column before converting the
+/// values), just ask the worksheet for its rows again. That is cheap: the worksheet's part is
+/// looked up by name, and the shared strings and styles the workbook already read are shared
+/// rather than read again.
+///
+/// # Example usage
+///
+///     use xl::Workbook;
+///
+///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+///     let sheets = wb.sheets();
+///     let ws = sheets.get("Sheet1").unwrap();
+///     let widest = ws.rows(&mut wb).unwrap().map(|row| row.0.len()).max().unwrap();
+///     for row in ws.rows(&mut wb).unwrap() {
+///         assert!(row.0.len() <= widest);
+///     }
+pub struct RowIter<'a> {
     worksheet_reader: SheetReader<'a>,
     want_row: usize,
-    next_row: Option<Row<'a>>,
+    /// Rows we have read from the xml but not returned yet, with the highest row first (see
+    /// `read_ahead`)
+    pending: Vec<Row<'a>>,
+    /// The row we last read ahead from (so we only do it once for each gap)
+    gap_checked: Option<usize>,
+    /// Only rows whose cell in this column passes this test are returned (see `filter_column`)
+    column_filter: Option<ColumnFilter<'a>>,
+    /// Rows the `column_filter` turned down that we have not got to yet (so we do not "simulate"
+    /// them as empty rows)
+    filtered_out: HashSet<usize>,
     num_rows: u32,
     num_cols: u16,
     done_file: bool,
+    /// If set, the (1-based) columns we want in each row (in the order we want them)
+    columns: Option<Vec<u16>>,
+    /// The highest row number we have found in the worksheet xml so far
+    last_row: usize,
+    dimension_mismatch: Option<DimensionMismatch>,
+    /// Rows that turned up after we had gone past them (see `late_rows`)
+    late_rows: Vec<usize>,
+    /// shared formulas (by their "si" index) along with the (col, row) of the cell that defined them
+    shared_formulas: HashMap<String, (String, u16, u32)>,
+    /// Rows before this one are skipped
+    first_row: usize,
+    /// Rows after this one are not returned (and we stop reading when we get there)
+    final_row: Option<usize>,
+    /// The most rows we should return
+    max_rows: Option<usize>,
+    rows_returned: usize,
+    /// The row `peek` looked at (if it has not been returned by `next` yet)
+    peeked: Option<Option<Row<'a>>>,
+    /// Keep returning empty rows after the last row with data, up to the last row the
+    /// worksheet's `dimension` claims
+    pad_to_dimension: bool,
+    /// Trim the whitespace around text values (see `ReadOptions::trim_strings`)
+    trim_strings: bool,
+    /// Also squeeze runs of whitespace inside text values (see `ReadOptions::collapse_whitespace`)
+    collapse_whitespace: bool,
+    /// Read formula text that spells out an error as that error (see
+    /// `ReadOptions::detect_error_text`)
+    detect_error_text: bool,
+    /// Read 2/29/1900 (serial 60) as a number rather than an error (see
+    /// `ReadOptions::treat_leap_bug_as_number`)
+    treat_leap_bug_as_number: bool,
+    /// Read numbers like "3,14" as 3.14 (see `ReadOptions::comma_decimal`)
+    comma_decimal: bool,
+    /// Only return the rows and cells the worksheet xml has, without "simulating" the empty ones
+    /// in between (see `Worksheet::cells`)
+    sparse: bool,
+}
+
+/// The test `RowIter::filter_column` applies to the cells in one column
+struct ColumnFilter<'a> {
+    /// The (1-based) column
+    col: u16,
+    test: Box<dyn FnMut(&Cell) -> bool + 'a>,
+}
+
+impl ColumnFilter<'_> {
+    /// Does the cell in our column of row `row_num` pass the test, if the row has no such cell?
+    fn passes_blank(&mut self, row_num: usize) -> bool {
+        (self.test)(&blank_cell(self.col, row_num))
+    }
+}
+
+/// Worksheets record their "used area" in a `dimension` element, and we use it to figure out how
+/// wide each row should be. Some programs that write xlsx files get it wrong, though, so when we
+/// reach the end of a worksheet we compare the number of rows it claimed to have with the number
+/// of rows we actually found. A `DimensionMismatch` records the difference (see
+/// `RowIter::dimension_mismatch`).
+#[derive(Debug, PartialEq)]
+pub struct DimensionMismatch {
+    /// The last row according to the worksheet's `dimension` element
+    pub declared_rows: u32,
+    /// The last row we actually found in the worksheet
+    pub actual_rows: u32,
+}
+
+impl RowIter<'_> {
+    /// Only read the (1-based) columns in `cols`. Each `Row` this iterator returns will then
+    /// contain exactly one cell per column in `cols`, in the order given, so `row[0]` is the cell
+    /// in column `cols[0]`. Cells in other columns are skipped while the worksheet is parsed, which
+    /// saves a lot of work when you only need a few columns of a wide worksheet. A column may be
+    /// listed more than once, in which case its cell shows up at each of its positions.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{Workbook, ExcelValue};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let mut rows = ws.rows(&mut wb).unwrap();
+    ///     rows.select_columns(&[3, 1]);
+    ///     let row1 = rows.next().unwrap();
+    ///     assert_eq!(row1.0.len(), 2);
+    ///     assert_eq!(row1[0].value, ExcelValue::Number(3f64));
+    ///     assert_eq!(row1[1].value, ExcelValue::Number(1f64));
+    pub fn select_columns(&mut self, cols: &[u16]) {
+        self.columns = Some(cols.to_vec());
+    }
+
+    /// Return the full grid the worksheet's `dimension` element describes. Normally we stop after
+    /// the last row that actually holds data, even when the worksheet claims to be bigger (some
+    /// programs declare all 1,048,576 rows!). With this, we keep returning empty rows until we
+    /// reach the last row of the dimension, so only use it if you trust the worksheet (or combine
+    /// it with `ReadOptions::max_rows`).
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/dimension_overstated.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     assert_eq!(ws.rows(&mut wb).unwrap().count(), 3);
+    ///     let mut rows = ws.rows(&mut wb).unwrap();
+    ///     rows.pad_to_dimension();
+    ///     assert_eq!(rows.count(), 10);
+    pub fn pad_to_dimension(&mut self) {
+        self.pad_to_dimension = true;
+    }
+
+    /// Once the iterator has read to the end of the worksheet, this tells you whether the number
+    /// of rows the worksheet claimed to have (in its `dimension` element) disagrees with the
+    /// number of rows it actually has. Returns `None` if they agree, if the worksheet has no
+    /// `dimension`, or if we have not reached the end of the worksheet yet.
+    ///
+    /// Note that we never "simulate" empty rows past the last row we actually find, even if the
+    /// `dimension` claims there are more, since there is no way to tell whether that claim is
+    /// trustworthy. Rows beyond a `dimension` that is too small are still returned.
+    pub fn dimension_mismatch(&self) -> Option<&DimensionMismatch> {
+        self.dimension_mismatch.as_ref()
+    }
+
+    /// The rows (so far) that turned up in the worksheet xml after we had already gone past them,
+    /// in the order we found them. These rows are left out of what the iterator returns, since
+    /// their spot has been taken by an empty row (or by an earlier row with the same number).
+    /// Only a malformed worksheet has any.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/out_of_order.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Late").unwrap();
+    ///     let mut rows = ws.rows(&mut wb).unwrap();
+    ///     assert_eq!(rows.by_ref().count(), 4);
+    ///     assert_eq!(rows.late_rows(), [2]);
+    pub fn late_rows(&self) -> &[usize] {
+        &self.late_rows
+    }
+
+    /// How many bytes of the worksheet xml we have read so far. This is a position in the
+    /// *decompressed* xml (not in the xlsx file itself), so compare it to `total_bytes` to see how
+    /// far along you are (e.g., to drive a progress bar).
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let mut rows = ws.rows(&mut wb).unwrap();
+    ///     rows.next();
+    ///     let percent = 100 * rows.bytes_position() / rows.total_bytes().unwrap();
+    ///     assert!(percent < 100);
+    pub fn bytes_position(&self) -> u64 {
+        self.worksheet_reader.reader.buffer_position() as u64
+    }
+
+    /// The size of the decompressed worksheet xml in bytes (see `bytes_position`). This is always
+    /// known for worksheets you get from a `Workbook`, but not when the `SheetReader` was built
+    /// by hand with `SheetReader::new`.
+    pub fn total_bytes(&self) -> Option<u64> {
+        self.worksheet_reader.size
+    }
 }
 
 fn new_cell() -> Cell<'static> {
@@ -244,64 +1522,243 @@ fn new_cell() -> Cell<'static> {
         formula: "".to_string(),
         reference: "".to_string(),
         style: "".to_string(),
-        cell_type: "".to_string(),
+        cell_type: CellType::Number,
         raw_value: "".to_string(),
+        shared_text: None,
     }
 }
 
-fn empty_row(num_cols: u16, this_row: usize) -> Option<Row<'static>> {
-    let mut row = vec![];
-    for n in 0..num_cols {
-        let mut c = new_cell();
-        c.reference.push_str(&utils::num2col(n + 1).unwrap());
-        c.reference.push_str(&this_row.to_string());
-        row.push(c);
+/// An empty cell at (`col`, `row`). The reference is left empty if `col` is not a valid column.
+fn blank_cell(col: u16, row: usize) -> Cell<'static> {
+    let mut c = new_cell();
+    if let Some(col) = utils::num2col(col) {
+        c.reference.push_str(&col);
+        c.reference.push_str(&row.to_string());
     }
+    c
+}
+
+fn empty_row(num_cols: u16, columns: Option<&[u16]>, this_row: usize) -> Option<Row<'static>> {
+    let row = match columns {
+        Some(cols) => cols.iter().map(|&col| blank_cell(col, this_row)).collect(),
+        None => (1..=cmp::min(num_cols, utils::XL_MAX_COL)).map(|col| blank_cell(col, this_row)).collect(),
+    };
     Some(Row(row, this_row))
 }
 
-impl<'a> Iterator for RowIter<'a> {
-    type Item = Row<'a>;
+/// The text of a string cell, with its whitespace trimmed and/or collapsed if the `ReadOptions`
+/// asked for it (only collapsing needs a copy of the text)
+fn tidy(text: &str, trim: bool, collapse: bool) -> Cow<'_, str> {
+    if collapse {
+        Cow::Owned(text.split_whitespace().collect::<Vec<_>>().join(" "))
+    } else if trim {
+        Cow::Borrowed(text.trim())
+    } else {
+        Cow::Borrowed(text)
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        // the xml in the xlsx file will not contain elements for empty rows. So
-        // we need to "simulate" the empty rows since the user expects to see
-        // them when they iterate over the worksheet.
-        if let Some(Row(_, row_num)) = &self.next_row {
-            // since we are currently buffering a row, we know we will either return it or a
-            // "simulated" (i.e., emtpy) row. So we grab the current row and update the fact that
-            // we will soon want a new row. We then figure out if we have the row we want or if we
-            // need to keep spitting out empty rows.
-            let current_row = self.want_row;
-            self.want_row += 1;
-            if *row_num == current_row {
-                // we finally hit the row we were looking for, so we reset the buffer and return
-                // the row that was sitting in it.
-                let mut r = None;
-                mem::swap(&mut r, &mut self.next_row);
-                return r
-            } else {
-                // otherwise, we must still be sitting behind the row we want. So we return an
-                // empty row to simulate the row that exists in the spreadsheet.
-                return empty_row(self.num_cols, current_row)
+impl<'a> RowIter<'a> {
+    /// Only return the rows for which `f` returns true. This is the same as `Iterator::filter`,
+    /// so every row is still read in full before `f` sees it (rows are not kept in memory,
+    /// though, so a selective extract of a big worksheet stays small). If your test only looks at
+    /// one column, `filter_column` is quicker.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let rows = ws.rows(&mut wb).unwrap().filter_rows(|row| row[7].is_string());
+    ///     assert_eq!(rows.map(|row| row.1).collect::<Vec<_>>(), vec![10, 16]);
+    pub fn filter_rows<F: FnMut(&Row<'a>) -> bool>(self, f: F) -> std::iter::Filter<Self, F> {
+        self.filter(f)
+    }
+
+    /// Only return the rows whose cell in the (1-based) column `col` passes `test`. The test is
+    /// run while each row is parsed: as soon as a cell fails it, the rest of its row is skipped
+    /// without decoding any more values, so picking a few rows out of a wide worksheet is much
+    /// quicker than with `filter_rows`. Rows without a cell in `col` (including the empty rows
+    /// we "simulate" for rows the worksheet leaves out) are tested with a blank cell. Calling this
+    /// again replaces the earlier test.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{Workbook, ExcelValue};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let mut rows = ws.rows(&mut wb).unwrap();
+    ///     rows.filter_column(1, |cell| cell.as_i64().is_some_and(|n| n > 750));
+    ///     let firsts: Vec<_> = rows.map(|row| row[0].value.to_string()).collect();
+    ///     assert_eq!(firsts, vec!["757", "775", "793", "811"]);
+    pub fn filter_column<F: FnMut(&Cell) -> bool + 'a>(&mut self, col: u16, test: F) {
+        self.column_filter = Some(ColumnFilter { col, test: Box::new(test) });
+    }
+
+    /// Should we return an empty row for row `row_num` (i.e., the worksheet has no row there)?
+    /// Not if the row was actually there and was turned down by `filter_column`, or if a blank
+    /// cell fails its test.
+    #[allow(clippy::unnecessary_map_or)] // `is_none_or` needs Rust 1.82
+    fn keep_empty_row(&mut self, row_num: usize) -> bool {
+        !self.filtered_out.remove(&row_num)
+            && self.column_filter.as_mut().map_or(true, |filter| filter.passes_blank(row_num))
+    }
+
+    /// Look at the next row without consuming it: the following call to `next` returns the same
+    /// row. Peeking sees exactly what `next` would, including the empty rows we "simulate" for
+    /// rows the worksheet xml leaves out and the effect of any `ReadOptions`. Once there are no
+    /// rows left, both `peek` and `next` keep returning `None`.
+    ///
+    /// Note that peeking reads the row from the worksheet, so `bytes_position` moves forward.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let mut rows = ws.rows(&mut wb).unwrap();
+    ///     assert_eq!(rows.peek().unwrap().1, 1);
+    ///     assert_eq!(rows.next().unwrap().1, 1);
+    ///     assert_eq!(rows.peek().unwrap().1, 2);
+    pub fn peek(&mut self) -> Option<&Row<'a>> {
+        if self.peeked.is_none() {
+            let row = self.advance();
+            self.peeked = Some(row);
+        }
+        self.peeked.as_ref().unwrap().as_ref()
+    }
+
+    /// The next row after the `ReadOptions` are applied (ignoring any row `peek` is holding)
+    fn advance(&mut self) -> Option<Row<'a>> {
+        loop {
+            if self.max_rows.is_some_and(|max| self.rows_returned >= max) {
+                return None
+            }
+            let row = self.read_row()?;
+            if row.1 < self.first_row {
+                continue
             }
-        } else if self.done_file && self.want_row < self.num_rows as usize {
+            if self.final_row.is_some_and(|last| row.1 > last) {
+                self.done_file = true;
+                self.pending.clear();
+                return None
+            }
+            self.rows_returned += 1;
+            return Some(row)
+        }
+    }
+
+    /// After the end of the worksheet xml, the empty rows `pad_to_dimension` asks for (if any)
+    fn padding_row(&mut self) -> Option<Row<'a>> {
+        while self.pad_to_dimension && self.want_row <= self.num_rows as usize {
             self.want_row += 1;
-            return empty_row(self.num_cols, self.want_row - 1)
+            if self.keep_empty_row(self.want_row - 1) {
+                return empty_row(self.num_cols, self.columns.as_deref(), self.want_row - 1)
+            }
+        }
+        None
+    }
+
+    /// Read the next row of the worksheet (before any of the `ReadOptions` are applied)
+    fn read_row(&mut self) -> Option<Row<'a>> {
+        loop {
+            self.read_ahead();
+            // the xml in the xlsx file will not contain elements for empty rows. So
+            // we need to "simulate" the empty rows since the user expects to see
+            // them when they iterate over the worksheet.
+            let row_num = match self.pending.last() {
+                Some(Row(_, row_num)) => *row_num,
+                None => return self.padding_row(),
+            };
+            if row_num > self.want_row && !self.sparse {
+                // we are still sitting behind the next row we have, so we return an empty row to
+                // simulate the row that exists in the spreadsheet
+                self.want_row += 1;
+                if self.keep_empty_row(self.want_row - 1) {
+                    return empty_row(self.num_cols, self.columns.as_deref(), self.want_row - 1)
+                }
+                continue
+            }
+            // a row that turns up after we have gone past it (see `read_ahead`) cannot go back to
+            // its spot, so we leave it out rather than return its number twice
+            if row_num < self.want_row {
+                self.pending.pop();
+                self.late_rows.push(row_num);
+                continue
+            }
+            self.want_row = row_num + 1;
+            return self.pending.pop()
+        }
+    }
+
+    /// Make sure `pending` holds the next row the worksheet xml has for us (if any). Rows almost
+    /// always come in order, so this normally reads one row at a time. Some programs write rows
+    /// out of order, though (e.g., row 5 before rows 3 and 4), so when there is a gap before the
+    /// lowest row we are holding, we read ahead until we find a row that is higher than all of
+    /// them. Rows that turn up in the meantime fill the gap, and `pending` puts them in order.
+    /// A row that turns up any later than that has already been "simulated" as an empty row, so
+    /// `read_row` leaves it out (and `late_rows` reports it).
+    fn read_ahead(&mut self) {
+        if self.pending.is_empty() {
+            match self.parse_row() {
+                Some(row) => self.pending.push(row),
+                None => return,
+            }
+        }
+        let lowest = self.pending[self.pending.len() - 1].1;
+        if lowest <= self.want_row || self.gap_checked == Some(lowest) {
+            return
+        }
+        self.gap_checked = Some(lowest);
+        let highest = self.pending[0].1;
+        while let Some(row) = self.parse_row() {
+            let row_num = row.1;
+            // rows with the same number stay in the order we found them
+            let pos = self.pending.partition_point(|Row(_, n)| *n > row_num);
+            self.pending.insert(pos, row);
+            if row_num > highest {
+                break
+            }
+        }
+    }
+
+    /// Parse the next row in the worksheet xml, in the order the xml has them (or `None` once we
+    /// reach the end)
+    fn parse_row(&mut self) -> Option<Row<'a>> {
+        if self.done_file {
+            return None
         }
         let mut buf = Vec::new();
         let reader = &mut self.worksheet_reader.reader;
         let strings = self.worksheet_reader.strings;
         let styles = self.worksheet_reader.styles;
         let date_system = self.worksheet_reader.date_system;
+        let decoder = &mut self.worksheet_reader.decoder;
         let next_row = {
             let mut row: Vec<Cell> = Vec::with_capacity(self.num_cols as usize);
-            let mut in_cell = false;
+            let mut in_formula = false;
+            let mut shared_index: Option<String> = None;
             let mut in_value = false;
+            let mut skip_cell = false;
+            // is the cell we are in the one `filter_column` tests? And has this row been tested
+            // (or turned down) yet?
+            let mut filter_cell = false;
+            let mut tested = false;
+            let mut rejected = false;
             let mut c = new_cell();
             let mut this_row: usize = 0;
+            // some programs leave out the `r` attribute on rows and cells and rely on the order
+            // they appear in the xml instead, so we keep track of where the next one would be
+            let mut next_col: u16 = 1;
             loop {
                 match reader.read_event(&mut buf) {
+                    Ok(Event::Decl(ref e)) => decoder.declared(e),
                     /* may be able to get a better estimate for the used area */
                     Ok(Event::Empty(ref e)) if e.name() == b"dimension" => {
                         if let Some(used_area_range) = utils::get(e.attributes(), b"ref") {
@@ -314,10 +1771,28 @@ impl<'a> Iterator for RowIter<'a> {
                     },
                     /* -- end search for used area */
                     Ok(Event::Start(ref e)) if e.name() == b"row" => {
-                        this_row = utils::get(e.attributes(), b"r").unwrap().parse().unwrap();
+                        this_row = match utils::get(e.attributes(), b"r") {
+                            Some(r) => r.parse().unwrap(),
+                            None => self.last_row + 1,
+                        };
+                        next_col = 1;
+                        tested = false;
+                        rejected = false;
+                        // Excel says which columns the rows around this one use (e.g.,
+                        // spans="1:5"), so we can pad this row out to the columns of the rows
+                        // after it rather than only the ones before it
+                        if let Some(last_col) = utils::get(e.attributes(), b"spans").and_then(|s| last_spanned_col(&s)) {
+                            self.num_cols = cmp::max(self.num_cols, last_col);
+                        }
+                    },
+                    Ok(Event::Empty(ref e)) if e.name() == b"c" => {
+                        // a cell without a value still takes up a spot in the row
+                        next_col = match utils::get(e.attributes(), b"r") {
+                            Some(r) => try_coordinates(&r).map_or(next_col, |(col, _)| col) + 1,
+                            None => next_col + 1,
+                        };
                     },
                     Ok(Event::Start(ref e)) if e.name() == b"c" => {
-                        in_cell = true;
                         e.attributes()
                             .for_each(|a| {
                                 let a = a.unwrap();
@@ -325,7 +1800,7 @@ impl<'a> Iterator for RowIter<'a> {
                                     c.reference = utils::attr_value(&a);
                                 }
                                 if a.key == b"t" {
-                                    c.cell_type = utils::attr_value(&a);
+                                    c.cell_type = CellType::from(&utils::attr_value(&a)[..]);
                                 }
                                 if a.key == b"s" {
                                     if let Ok(num) = utils::attr_value(&a).parse::<usize>() {
@@ -335,112 +1810,283 @@ impl<'a> Iterator for RowIter<'a> {
                                     }
                                 }
                             });
+                        if c.reference.is_empty() {
+                            if let Some(col) = utils::num2col(next_col) {
+                                c.reference.push_str(&col);
+                                c.reference.push_str(&this_row.to_string());
+                            }
+                        }
+                        // cells past the last column Excel allows (e.g., XFE1) can only come from
+                        // a malformed file, so we skip them rather than fail
+                        match try_coordinates(&c.reference) {
+                            Some((this_col, _)) => {
+                                next_col = this_col.saturating_add(1);
+                                filter_cell = self.column_filter.as_ref().is_some_and(|f| f.col == this_col);
+                                tested |= filter_cell;
+                                if let Some(cols) = &self.columns {
+                                    skip_cell = !cols.contains(&this_col) && !filter_cell;
+                                }
+                                // nothing else in a row that was turned down needs reading
+                                skip_cell |= rejected;
+                            },
+                            None => skip_cell = true,
+                        }
                     },
                     Ok(Event::Start(ref e)) if e.name() == b"v" || e.name() == b"t" => {
                         in_value = true;
                     },
-                    // note: because v elements are children of c elements,
-                    // need this check to go before the 'in_cell' check
+                    Ok(Event::Start(ref e)) if e.name() == b"f" => {
+                        in_formula = true;
+                        shared_index = shared_formula_index(e);
+                    },
+                    Ok(Event::Empty(ref e)) if e.name() == b"f" && !skip_cell => {
+                        if let Some(si) = shared_formula_index(e) {
+                            c.formula = shared_formula(&self.shared_formulas, &si, &c);
+                        }
+                    },
+                    // we don't need to decode anything in a cell outside of the columns we want,
+                    // except for the formula of a shared formula's master (see below)
+                    Ok(Event::Text(_)) if skip_cell && !(in_formula && shared_index.is_some()) => (),
                     Ok(Event::Text(ref e)) if in_value => {
-                        c.raw_value = e.unescape_and_decode(&reader).unwrap();
-                        c.value = match &c.cell_type[..] {
-                            "s" => {
-                                if let Ok(pos) = c.raw_value.parse::<usize>() {
-                                    let s = &strings[pos]; // .to_string()
-                                    ExcelValue::String(Cow::Borrowed(s))
-                                } else {
-                                    ExcelValue::String(Cow::Owned(c.raw_value.clone()))
-                                }
+                        c.raw_value = decoder.unescape(e.escaped());
+                        c.value = match c.cell_type {
+                            CellType::Shared => match c.raw_value.parse().ok().and_then(|pos: usize| strings.get(pos)) {
+                                // the text is borrowed from the shared strings unless it has to be
+                                // changed (see `tidy`)
+                                Some(text) => {
+                                    c.shared_text = Some(text);
+                                    ExcelValue::String(tidy(text, self.trim_strings, self.collapse_whitespace))
+                                },
+                                // an index that is not in the shared strings (e.g., ones supplied
+                                // with `Workbook::with_shared_strings` for another file) keeps the
+                                // raw value
+                                None => ExcelValue::String(Cow::Owned(
+                                    tidy(&c.raw_value, self.trim_strings, self.collapse_whitespace).into_owned())),
                             },
-                            "str" | "inlineStr" => {
-                                ExcelValue::String(Cow::Owned(c.raw_value.clone()))
+                            CellType::Str if self.detect_error_text && CellError::is_canonical(&c.raw_value) => {
+                                ExcelValue::Error(CellError::from(c.raw_value.as_str()))
                             },
-                            "b" => {
-                                if c.raw_value == "0" {
-                                    ExcelValue::Bool(false)
-                                } else {
-                                    ExcelValue::Bool(true)
-                                }
+                            CellType::Str | CellType::InlineStr => {
+                                ExcelValue::String(Cow::Owned(tidy(&c.raw_value, self.trim_strings, self.collapse_whitespace).into_owned()))
                             },
-                            "bl" => ExcelValue::None,
-                            "e" => ExcelValue::Error(c.raw_value.to_string()),
-                            _ if is_date(&c) => {
-                                let num = c.raw_value.parse::<f64>().unwrap();
-                                match utils::excel_number_to_date(num, date_system) {
-                                    utils::DateConversion::Date(date) => ExcelValue::Date(date),
-                                    utils::DateConversion::DateTime(date) => ExcelValue::DateTime(date),
-                                    utils::DateConversion::Time(time) => ExcelValue::Time(time),
-                                    utils::DateConversion::Number(num) => ExcelValue::Number(num as f64),
-                                }
-                                
+                            CellType::Bool => {
+                                // Excel writes 1/0, but some programs write out TRUE/FALSE
+                                let raw = &c.raw_value;
+                                ExcelValue::Bool(raw == "1" || raw.eq_ignore_ascii_case("true"))
                             },
-                            _ => ExcelValue::Number(c.raw_value.parse::<f64>().unwrap()),
+                            CellType::Error => ExcelValue::Error(CellError::from(c.raw_value.as_str())),
+                            // a date written out in ISO 8601 form rather than as a serial number
+                            CellType::Date => iso_date_value(&c.raw_value),
+                            // "n" (or no type at all) is a number, and so is a type we do not know
+                            // about, if it holds one; both may be dates depending on the style
+                            CellType::Number | CellType::Other(_) =>
+                                number_value(&c, date_system, self.treat_leap_bug_as_number, self.comma_decimal),
                         };
                     },
-                    Ok(Event::Text(ref e)) if in_cell => {
-                        let txt = e.unescape_and_decode(&reader).unwrap();
+                    Ok(Event::Text(ref e)) if in_formula => {
+                        let txt = decoder.unescape(e.escaped());
                         c.formula.push_str(&txt)
                     },
                     Ok(Event::End(ref e)) if e.name() == b"v" || e.name() == b"t" => {
                         in_value = false;
                     },
-                    Ok(Event::End(ref e)) if e.name() == b"c" => {
-                        if let Some(prev) = row.last() {
-                            let (mut last_col, _) = prev.coordinates();
-                            let (this_col, this_row) = c.coordinates();
-                            while this_col > last_col + 1 {
-                                let mut cell = new_cell();
-                                cell.reference.push_str(&utils::num2col(last_col + 1).unwrap());
-                                cell.reference.push_str(&this_row.to_string());
-                                row.push(cell);
-                                last_col += 1;
+                    Ok(Event::End(ref e)) if e.name() == b"f" => {
+                        in_formula = false;
+                        if let Some(si) = shared_index.take() {
+                            if c.formula.is_empty() {
+                                if !skip_cell {
+                                    c.formula = shared_formula(&self.shared_formulas, &si, &c);
+                                }
+                            } else if let Some((col, row)) = try_coordinates(&c.reference) {
+                                // the master is recorded even when we skip its cell, since the
+                                // cells sharing its formula may be in columns we do want
+                                self.shared_formulas.insert(si, (c.formula.clone(), col, row));
                             }
-                            row.push(c);
+                        }
+                    },
+                    Ok(Event::End(ref e)) if e.name() == b"c" && skip_cell => {
+                        c = new_cell();
+                        skip_cell = false;
+                    },
+                    Ok(Event::End(ref e)) if e.name() == b"c" && filter_cell
+                        && self.column_filter.as_mut().is_some_and(|f| !(f.test)(&c)) => {
+                        // the row fails the `filter_column` test, so we skip the rest of it
+                        rejected = true;
+                        filter_cell = false;
+                        c = new_cell();
+                    },
+                    Ok(Event::End(ref e)) if e.name() == b"c" && (self.columns.is_some() || self.sparse) => {
+                        // we line up selected columns when the row ends, and sparse rows have no
+                        // gaps to fill
+                        row.push(c);
+                        c = new_cell();
+                    },
+                    Ok(Event::End(ref e)) if e.name() == b"c" => {
+                        // `row[i]` always holds the cell in column i + 1. Cells usually come in
+                        // column order, but some programs write them in any order, so we put each
+                        // one where it belongs (filling any gap before it with blanks).
+                        let (this_col, this_row) = c.coordinates();
+                        let pos = this_col as usize - 1;
+                        while row.len() < pos {
+                            row.push(blank_cell(row.len() as u16 + 1, this_row as usize));
+                        }
+                        if pos < row.len() {
+                            row[pos] = c;
                         } else {
-                            let (this_col, this_row) = c.coordinates();
-                            for n in 1..this_col {
-                                let mut cell = new_cell();
-                                cell.reference.push_str(&utils::num2col(n).unwrap());
-                                cell.reference.push_str(&this_row.to_string());
-                                row.push(cell);
-                            }
                             row.push(c);
                         }
                         c = new_cell();
-                        in_cell = false;
+                    },
+                    Ok(Event::End(ref e)) if e.name() == b"row"
+                        && (rejected || !tested && self.column_filter.as_mut().is_some_and(|f| !f.passes_blank(this_row))) => {
+                        // the row was turned down by `filter_column`, so we go on to the next one
+                        // (making sure we do not "simulate" it as an empty row instead)
+                        if this_row >= self.want_row {
+                            self.filtered_out.insert(this_row);
+                        }
+                        self.last_row = cmp::max(self.last_row, this_row);
+                        row.clear();
+                    },
+                    Ok(Event::End(ref e)) if e.name() == b"row" && self.columns.is_some() => {
+                        let cols = self.columns.as_ref().unwrap();
+                        let mut selected = Vec::with_capacity(cols.len());
+                        for (i, &col) in cols.iter().enumerate() {
+                            let cell = match row.iter().position(|cell| cell.coordinates().0 == col) {
+                                // a column listed more than once gets a copy of its cell until its
+                                // last position, which can have the cell itself
+                                Some(pos) if cols[i + 1..].contains(&col) => row[pos].clone(),
+                                Some(pos) => row.swap_remove(pos),
+                                None => blank_cell(col, this_row),
+                            };
+                            selected.push(cell);
+                        }
+                        self.last_row = cmp::max(self.last_row, this_row);
+                        break Some(Row(selected, this_row))
                     },
                     Ok(Event::End(ref e)) if e.name() == b"row" => {
-                        self.num_cols = cmp::max(self.num_cols, row.len() as u16);
-                        while row.len() < self.num_cols as usize {
-                            let mut cell = new_cell();
-                            cell.reference.push_str(&utils::num2col(row.len() as u16 + 1).unwrap());
-                            cell.reference.push_str(&this_row.to_string());
-                            row.push(cell);
+                        if !self.sparse {
+                            self.num_cols = cmp::max(self.num_cols, row.len() as u16);
+                            while row.len() < self.num_cols as usize {
+                                row.push(blank_cell(row.len() as u16 + 1, this_row));
+                            }
                         }
-                        let next_row = Some(Row(row, this_row));
-                        if this_row == self.want_row {
-                            break next_row
-                        } else {
-                            self.next_row = next_row;
-                            break empty_row(self.num_cols, self.want_row)
+                        self.last_row = cmp::max(self.last_row, this_row);
+                        break Some(Row(row, this_row))
+                    },
+                    Ok(Event::Eof) => {
+                        self.done_file = true;
+                        if self.num_rows > 0 && self.num_rows as usize != self.last_row {
+                            self.dimension_mismatch = Some(DimensionMismatch {
+                                declared_rows: self.num_rows,
+                                actual_rows: self.last_row as u32,
+                            });
                         }
+                        break None
                     },
-                    Ok(Event::Eof) => break None,
                     Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
                     _ => (),
                 }
                 buf.clear();
             }
         };
-        self.want_row += 1;
-        if next_row.is_none() && self.want_row - 1 < self.num_rows as usize {
-            self.done_file = true;
-            return empty_row(self.num_cols, self.want_row - 1);
+        next_row
+    }
+}
+
+impl<'a> Iterator for RowIter<'a> {
+    type Item = Row<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.peeked.take() {
+            Some(row) => row,
+            None => self.advance(),
         }
-        next_row
     }
 }
 
+/// Once a `RowIter` returns `None` it keeps returning `None`: rows are only "simulated" up to the
+/// last row actually found in the worksheet (or, with `pad_to_dimension`, up to the last row of
+/// the `dimension`), and reaching the end of the xml, the last row of a range, or `max_rows` is
+/// final.
+impl FusedIterator for RowIter<'_> {}
+
+/// The last column in the `spans` attribute of a row (e.g., 5 for "1:5", or 7 for "1:3 5:7"), or
+/// `None` if it does not hold a column Excel allows
+fn last_spanned_col(spans: &str) -> Option<u16> {
+    spans.split_whitespace()
+        .filter_map(|span| span.split_once(':')?.1.parse().ok())
+        .filter(|col| (1..=utils::XL_MAX_COL).contains(col))
+        .max()
+}
+
+/// Return the "si" index of an `f` element if it is a shared formula
+fn shared_formula_index(e: &BytesStart) -> Option<String> {
+    if utils::get(e.attributes(), b"t")? == "shared" {
+        utils::get(e.attributes(), b"si")
+    } else {
+        None
+    }
+}
+
+/// Rebuild the formula of `cell` from the shared formula it points to
+fn shared_formula(shared: &HashMap<String, (String, u16, u32)>, si: &str, cell: &Cell) -> String {
+    match shared.get(si) {
+        Some((formula, master_col, master_row)) => {
+            let (col, row) = cell.coordinates();
+            let rows = row as i64 - *master_row as i64;
+            let cols = col as i64 - *master_col as i64;
+            utils::shift_formula(formula, rows, cols)
+        },
+        None => String::new(),
+    }
+}
+
+/// The value of a number cell: a date, time, or date and time if the cell's style makes it look
+/// like one, and a plain number otherwise
+fn number_value(cell: &Cell, date_system: &DateSystem, leap_bug_as_number: bool, comma_decimal: bool) -> ExcelValue<'static> {
+    let parsed = cell.raw_value.parse::<f64>().or_else(|e| {
+        if comma_decimal { cell.raw_value.replace(',', ".").parse() } else { Err(e) }
+    });
+    let num = match parsed {
+        Ok(num) => num,
+        // only a cell with a type we do not know about should get here
+        Err(_) => return ExcelValue::String(Cow::Owned(cell.raw_value.clone())),
+    };
+    if !is_date(cell) {
+        return ExcelValue::Number(num)
+    }
+    match utils::excel_number_to_date(num, date_system) {
+        Ok(utils::DateConversion::Date(date)) => ExcelValue::Date(date),
+        Ok(utils::DateConversion::DateTime(date)) => ExcelValue::DateTime(date),
+        Ok(utils::DateConversion::Time(time)) => ExcelValue::Time(time),
+        Ok(utils::DateConversion::Number(num)) => ExcelValue::Number(num as f64),
+        // the only serial that is not a date is Excel's 2/29/1900
+        Err(_) if leap_bug_as_number => ExcelValue::Number(num),
+        Err(e) => ExcelValue::Error(CellError::Other(e)),
+    }
+}
+
+/// The value of a cell of type "d", which holds a date, a date and time, or a time in ISO 8601
+/// form (e.g., "2021-03-07T15:04:05"). Excel never writes these, but other programs do. A date
+/// and time at midnight is read as a plain date, the same as a date stored as a serial number.
+/// Text we cannot read as a date is left as text.
+fn iso_date_value(raw: &str) -> ExcelValue<'static> {
+    let text = raw.trim_end_matches('Z');
+    if let Ok(dt) = NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f") {
+        if dt.time() == NaiveTime::from_hms_opt(0, 0, 0).unwrap() {
+            return ExcelValue::Date(dt.date())
+        }
+        return ExcelValue::DateTime(dt)
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return ExcelValue::Date(date)
+    }
+    if let Ok(time) = NaiveTime::parse_from_str(text, "%H:%M:%S%.f") {
+        return ExcelValue::Time(time)
+    }
+    ExcelValue::String(Cow::Owned(raw.to_owned()))
+}
+
 fn is_date(cell: &Cell) -> bool {
     let is_d = cell.style == "d";
     let is_like_d_and_not_like_red = cell.style.contains('d') && !cell.style.contains("Red");
@@ -455,6 +2101,7 @@ fn is_date(cell: &Cell) -> bool {
 #[cfg(test)]
 mod tests {
     use crate::{ExcelValue, Workbook};
+    use super::{last_spanned_col, Cell, CellError, CellType, DimensionMismatch, ReadOptions, Worksheet, XlError};
     use std::borrow::Cow;
 
     #[test]
@@ -462,10 +2109,823 @@ mod tests {
         let mut wb = Workbook::open("./tests/data/UPS.Galaxy.VS.PX.xlsx").unwrap();
         let sheets = wb.sheets();
         let ws = sheets.get("Table001 (Page 1-19)").unwrap();
-        let mut row_iter = ws.rows(&mut wb);
+        let mut row_iter = ws.rows(&mut wb).unwrap();
         let row2 = row_iter.nth(1).unwrap();
         assert_eq!(row2[3].value, ExcelValue::Number(0.0));
         let row3 = row_iter.next().unwrap();
         assert_eq!(row3[4].value, ExcelValue::String(Cow::Borrowed("Bit")));
     }
+
+    #[test]
+    fn for_each_cell() {
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let mut count = 0;
+        let mut b1 = String::new();
+        ws.for_each_cell(&mut wb, |cell| {
+            count += 1;
+            if cell.reference == "B1" {
+                b1.push_str(cell.raw_value);
+            }
+        }).unwrap();
+        assert_eq!(b1, "2");
+        let rows = ws.rows(&mut wb).unwrap().count();
+        assert!(count > rows);
+    }
+
+    #[test]
+    fn for_each_cell_bad_xml() {
+        let mut wb = Workbook::open("tests/data/bad_xml.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Broken").unwrap();
+        let mut count = 0;
+        assert!(matches!(ws.for_each_cell(&mut wb, |_| count += 1), Err(XlError::Xml(..))));
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn select_columns() {
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let mut rows = ws.rows(&mut wb).unwrap();
+        rows.select_columns(&[18, 2, 20]);
+        let row2 = rows.nth(1).unwrap();
+        assert_eq!(row2.0.len(), 3);
+        assert_eq!(row2[0].reference, "R2");
+        assert_eq!(row2[0].value, ExcelValue::Number(36.0));
+        assert_eq!(row2[1].value, ExcelValue::Number(20.0));
+        assert_eq!(row2[2].reference, "T2");
+        assert_eq!(row2[2].value, ExcelValue::None);
+    }
+
+    #[test]
+    fn select_column_twice() {
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let mut rows = ws.rows(&mut wb).unwrap();
+        rows.select_columns(&[18, 2, 18]);
+        let row2 = rows.nth(1).unwrap();
+        assert_eq!(row2[0].value, ExcelValue::Number(36.0));
+        assert_eq!(row2[2].reference, "R2");
+        assert_eq!(row2[2].value, ExcelValue::Number(36.0));
+    }
+
+    #[test]
+    fn dimension_overstated() {
+        let mut wb = Workbook::open("./tests/data/dimension_overstated.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let mut rows = ws.rows(&mut wb).unwrap();
+        assert_eq!(rows.by_ref().count(), 3);
+        assert_eq!(rows.dimension_mismatch(), Some(&DimensionMismatch { declared_rows: 10, actual_rows: 3 }));
+    }
+
+    #[test]
+    fn dimension_understated() {
+        let mut wb = Workbook::open("./tests/data/dimension_understated.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let mut rows = ws.rows(&mut wb).unwrap();
+        assert!(rows.dimension_mismatch().is_none());
+        let last = rows.by_ref().last().unwrap();
+        assert_eq!(last.1, 4);
+        assert_eq!(last[1].value, ExcelValue::Number(40.0));
+        assert_eq!(rows.dimension_mismatch(), Some(&DimensionMismatch { declared_rows: 2, actual_rows: 4 }));
+    }
+
+    #[test]
+    fn dimension_matches() {
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let mut rows = ws.rows(&mut wb).unwrap();
+        assert_eq!(rows.by_ref().count(), 46);
+        assert!(rows.dimension_mismatch().is_none());
+    }
+
+    #[test]
+    fn missing_references() {
+        let mut wb = Workbook::open("./tests/data/no_references.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let rows: Vec<_> = ws.rows(&mut wb).unwrap().collect();
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[0][2].reference, "C1");
+        assert_eq!(rows[0][2].value, ExcelValue::Number(3.0));
+        assert_eq!(rows[1][1].value, ExcelValue::None);
+        assert_eq!(rows[1][2].reference, "C2");
+        assert_eq!(rows[1][2].value, ExcelValue::Number(6.0));
+        assert_eq!(rows[4][0].reference, "A5");
+        assert_eq!(rows[4][3].reference, "D5");
+        assert_eq!(rows[4][3].value, ExcelValue::Number(10.0));
+    }
+
+    #[test]
+    fn booleans() {
+        let mut wb = Workbook::open("./tests/data/booleans.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        let values: Vec<_> = row.0.iter().map(|c| &c.value).collect();
+        let (t, f) = (ExcelValue::Bool(true), ExcelValue::Bool(false));
+        assert_eq!(values, vec![&t, &f, &t, &f, &t, &f]);
+    }
+
+    #[test]
+    fn formulas() {
+        let mut wb = Workbook::open("./tests/data/formulas.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert_eq!(row[0].formula, "");
+        assert_eq!(row[2].formula, "A1+B1");
+        assert_eq!(row[2].value, ExcelValue::Number(3.0));
+        assert_eq!(row[3].formula, "\"x\"&A1");
+        assert_eq!(row[3].value, ExcelValue::String(Cow::Borrowed("x1")));
+    }
+
+    #[test]
+    fn shared_formulas() {
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let mut rows = ws.rows(&mut wb).unwrap();
+        let row1 = rows.next().unwrap();
+        assert_eq!(row1[2].formula, "B1 + 1");
+        assert_eq!(row1[3].formula, "C1 + 1");
+        assert_eq!(row1[17].formula, "Q1 + 1");
+        let row2 = rows.next().unwrap();
+        assert_eq!(row2[3].formula, "C2 + 1");
+    }
+
+    #[test]
+    fn shared_formula_master_not_selected() {
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let mut rows = ws.rows(&mut wb).unwrap();
+        // the formula is only written out in C1, which we do not read
+        rows.select_columns(&[4, 18]);
+        let row1 = rows.next().unwrap();
+        assert_eq!(row1[0].formula, "C1 + 1");
+        assert_eq!(row1[1].formula, "Q1 + 1");
+        let row2 = rows.next().unwrap();
+        assert_eq!(row2[0].formula, "C2 + 1");
+    }
+
+    #[test]
+    fn leap_day_does_not_abort() {
+        let mut wb = Workbook::open("./tests/data/leapday.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let mut row_iter = ws.rows(&mut wb).unwrap();
+        let row1 = row_iter.next().unwrap();
+        assert!(matches!(row1[1].value, ExcelValue::Error(CellError::Other(_))));
+        assert_eq!(row1[2].value, ExcelValue::Date(chrono::NaiveDate::from_ymd_opt(1900, 3, 1).unwrap()));
+        let row2 = row_iter.next().unwrap();
+        assert_eq!(row2[0].value, ExcelValue::Number(60.0));
+    }
+
+    #[test]
+    fn leap_day_as_number() {
+        let mut wb = Workbook::open("./tests/data/leapday.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let opts = ReadOptions::new().treat_leap_bug_as_number(true);
+        let rows: Vec<_> = ws.rows_with(&mut wb, &opts).unwrap().collect();
+        assert_eq!(rows[0][0].value, ExcelValue::Date(chrono::NaiveDate::from_ymd_opt(1900, 2, 28).unwrap()));
+        assert_eq!(rows[0][1].value, ExcelValue::Number(60.0));
+        assert_eq!(rows[0][2].value, ExcelValue::Date(chrono::NaiveDate::from_ymd_opt(1900, 3, 1).unwrap()));
+        assert_eq!(rows[1][0].value, ExcelValue::Number(60.0));
+    }
+
+    #[test]
+    fn comma_decimal() {
+        let mut wb = Workbook::open("./tests/data/comma_decimal.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert_eq!(row[0].value, ExcelValue::String("2,75".into()));
+        assert_eq!(row[1].value, ExcelValue::Number(2.5));
+        let opts = ReadOptions::new().comma_decimal(true);
+        let row = ws.rows_with(&mut wb, &opts).unwrap().next().unwrap();
+        assert_eq!(row[0].value, ExcelValue::Number(2.75));
+        assert_eq!(row[0].raw_value, "2,75");
+        assert_eq!(row[1].value, ExcelValue::Number(2.5));
+        assert_eq!(row[2].value, ExcelValue::Number(-0.5));
+        assert_eq!(row[3].value, ExcelValue::String("1,234,5".into()));
+    }
+
+    #[test]
+    fn error_cells() {
+        let mut wb = Workbook::open("./tests/data/errors.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        for (i, error) in CellError::CANONICAL.iter().enumerate() {
+            assert_eq!(row[i as u16 + 1].value, ExcelValue::Error(error.clone()));
+            assert_eq!(row[i as u16 + 1].value.to_string(), error.as_str());
+        }
+        assert_eq!(row[11].value, ExcelValue::Error(CellError::Other("#BOGUS!".to_owned())));
+    }
+
+    #[test]
+    fn error_forms() {
+        for text in &["#DIV/0!", "DIV/0!", "##DIV/0!"] {
+            let error = CellError::from(*text);
+            assert_eq!(error, CellError::Div0, "{}", text);
+            assert_eq!(ExcelValue::Error(error).to_string(), "#DIV/0!");
+        }
+        for text in &["#BOGUS!", "BOGUS!", "##BOGUS!"] {
+            assert_eq!(CellError::from(*text), CellError::Other("#BOGUS!".to_owned()), "{}", text);
+        }
+        assert_eq!(CellError::from("N/A").to_string(), "#N/A");
+    }
+
+    #[test]
+    fn headers() {
+        let mut wb = Workbook::open("./tests/data/errors.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let headers = ws.headers(&mut wb).unwrap();
+        assert_eq!(headers[0], "errors");
+        assert_eq!(headers[2], "#DIV/0!");
+        let mut wb = Workbook::open("./tests/data/dimension_understated.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let headers = ws.headers(&mut wb).unwrap();
+        assert_eq!(headers.len(), 2);
+    }
+
+    #[test]
+    fn missing_sheet_part() {
+        let mut wb = Workbook::open("./tests/data/missing_sheet.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let broken = sheets.get("Broken").unwrap();
+        let err = broken.rows(&mut wb).err().unwrap();
+        assert_eq!(err, XlError::MissingPart("xl/worksheets/sheet2.xml".to_owned()));
+        assert!(broken.for_each_cell(&mut wb, |_| ()).is_err());
+        let ws = sheets.get("Sheet1").unwrap();
+        assert_eq!(ws.rows(&mut wb).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn is_empty() {
+        let mut wb = Workbook::open("./tests/data/empty_sheets.xlsx").unwrap();
+        let sheets = wb.sheets();
+        assert!(sheets.get("Blank").unwrap().is_empty(&mut wb).unwrap());
+        assert!(sheets.get("Formatted").unwrap().is_empty(&mut wb).unwrap());
+        assert!(!sheets.get("Data").unwrap().is_empty(&mut wb).unwrap());
+    }
+
+    #[test]
+    fn is_empty_bad_xml() {
+        let mut wb = Workbook::open("tests/data/bad_xml.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Broken").unwrap();
+        assert!(matches!(ws.is_empty(&mut wb), Err(XlError::Xml(..))));
+    }
+
+    #[test]
+    fn bytes_position() {
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let mut rows = ws.rows(&mut wb).unwrap();
+        assert_eq!(rows.bytes_position(), 0);
+        rows.next();
+        let after_first = rows.bytes_position();
+        assert!(after_first > 0);
+        for _ in rows.by_ref() {}
+        assert!(rows.bytes_position() > after_first);
+        assert_eq!(Some(rows.bytes_position()), rows.total_bytes());
+    }
+
+    #[test]
+    fn non_utf8_text_does_not_panic() {
+        let mut wb = Workbook::open("./tests/data/windows1252.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert_eq!(row.0.len(), 2);
+        if cfg!(not(feature = "encoding")) {
+            assert_eq!(row[0].value, ExcelValue::String("caf\u{fffd}".into()));
+        }
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn declared_encoding_is_used() {
+        let mut wb = Workbook::open("./tests/data/windows1252.xlsx").unwrap();
+        assert_eq!(wb.sheet_names(), vec!["Résumé & co"]);
+        let sheets = wb.sheets();
+        let ws = sheets.get("Résumé & co").unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert_eq!(row[0].value, ExcelValue::String("café".into()));
+        assert_eq!(row[1].value, ExcelValue::String("naïve".into()));
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn forced_encoding() {
+        let mut wb = Workbook::open_with_encoding("./tests/data/Book1.xlsx", "latin1").unwrap();
+        assert_eq!(wb.sheet_names()[0], "Sheet1");
+        assert!(Workbook::open_with_encoding("./tests/data/Book1.xlsx", "klingon").is_err());
+    }
+
+    #[test]
+    fn rows_with_options() {
+        let mut wb = Workbook::open("./tests/data/dimension_understated.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let opts = ReadOptions::new().range("B3:B4");
+        let rows: Vec<_> = ws.rows_with(&mut wb, &opts).unwrap().collect();
+        let refs: Vec<_> = rows.iter().map(|r| &r[0].reference[..]).collect();
+        assert_eq!(refs, vec!["B3", "B4"]);
+        let opts = ReadOptions::new().max_rows(0);
+        assert_eq!(ws.rows_with(&mut wb, &opts).unwrap().count(), 0);
+        let opts = ReadOptions::new().columns(&["XFE"]);
+        assert!(matches!(ws.rows_with(&mut wb, &opts), Err(XlError::BadOption(_))));
+    }
+
+    #[test]
+    fn cells_past_last_column() {
+        let mut wb = Workbook::open("./tests/data/past_last_column.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let rows: Vec<_> = ws.rows(&mut wb).unwrap().collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].0.len(), 16384);
+        assert_eq!(rows[0][0].value, ExcelValue::Number(1.0));
+        assert_eq!(rows[0][16383].reference, "XFD1");
+        assert_eq!(rows[0][16383].value, ExcelValue::Number(3.0));
+        assert_eq!(rows[1][1].value, ExcelValue::Number(5.0));
+        assert_eq!(rows[2].0.len(), 16384);
+    }
+
+    #[test]
+    fn cells_out_of_order() {
+        let mut wb = Workbook::open("./tests/data/shuffled_cells.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let rows: Vec<_> = ws.rows(&mut wb).unwrap().collect();
+        for row in &rows {
+            assert_eq!(row.0.len(), 4);
+            for (i, cell) in row.0.iter().enumerate() {
+                assert_eq!(cell.coordinates(), (i as u16 + 1, row.1 as u32));
+            }
+        }
+        let values: Vec<_> = rows[0].0.iter().map(|c| c.value.to_string()).collect();
+        assert_eq!(values, vec!["1", "", "3", "4"]);
+        let values: Vec<_> = rows[1].0.iter().map(|c| c.value.to_string()).collect();
+        assert_eq!(values, vec!["", "6", "", "8"]);
+    }
+
+    #[test]
+    fn formatted_values() {
+        let mut wb = Workbook::open("./tests/data/formatted.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        let values: Vec<_> = row.0.iter().map(|c| c.formatted_value()).collect();
+        assert_eq!(values, vec!["$1,234.50", "7-Mar-21", "12.50%", "(42)", "3.5"]);
+    }
+
+    #[test]
+    fn images() {
+        let mut wb = Workbook::open("./tests/data/images.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let images = sheets.get("Sheet1").unwrap().images(&mut wb);
+        assert_eq!(images.len(), 2);
+        assert_eq!((images[0].anchor.col, images[0].anchor.row), (2, 3));
+        assert!(images[0].bytes.starts_with(b"\x89PNG"));
+        assert_eq!((images[1].anchor.col, images[1].anchor.row), (5, 1));
+        assert_eq!(images[1].mime, "image/jpeg");
+        assert!(sheets.get("Sheet2").unwrap().images(&mut wb).is_empty());
+    }
+
+    #[test]
+    fn data_validations() {
+        let mut wb = Workbook::open("./tests/data/validations.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let validations = sheets.get("Sheet1").unwrap().data_validations(&mut wb);
+        assert_eq!(validations.len(), 4);
+        assert_eq!(validations[1].kind, "whole");
+        assert_eq!(validations[1].sqref, vec!["C2:C10", "E2"]);
+        assert_eq!(validations[1].formula2.as_deref(), Some("10"));
+        assert_eq!(validations[2].formula1.as_deref(), Some("$F$1:$F$3"));
+        assert_eq!(validations[3].sqref, vec!["G2:G20"]);
+        assert_eq!(validations[3].formula1.as_deref(), Some("Lists!$A$1:$A$3"));
+        assert!(sheets.get("Lists").unwrap().data_validations(&mut wb).is_empty());
+    }
+
+    #[test]
+    fn data_validations_bad_xml() {
+        let mut wb = Workbook::open("tests/data/bad_xml.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let validations = sheets.get("Broken").unwrap().data_validations(&mut wb);
+        assert_eq!(validations.len(), 1);
+        assert_eq!(validations[0].sqref, vec!["A1:A10"]);
+    }
+
+    #[test]
+    fn tab_colors() {
+        let mut wb = Workbook::open("./tests/data/tab_colors.xlsx").unwrap();
+        let sheets = wb.sheets();
+        assert_eq!(sheets.get("Theme").unwrap().tab_color(&mut wb), Some("theme:5".to_owned()));
+        assert_eq!(sheets.get("Plain").unwrap().tab_color(&mut wb), None);
+    }
+
+    #[test]
+    fn peek_matches_next() {
+        // the Time sheet leaves out every other row, so peeking has to see the simulated empty rows
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Time").unwrap();
+        let expected: Vec<_> = ws.rows(&mut wb).unwrap().map(|r| r.to_string()).collect();
+        let mut rows = ws.rows(&mut wb).unwrap();
+        let mut seen = vec![];
+        while let Some(peeked) = rows.peek().map(|r| r.to_string()) {
+            assert_eq!(rows.peek().unwrap().to_string(), peeked);
+            assert_eq!(rows.next().unwrap().to_string(), peeked);
+            seen.push(peeked);
+        }
+        assert_eq!(seen, expected);
+        assert!(seen.iter().any(|r| r.is_empty()));
+        assert!(rows.next().is_none());
+        assert!(rows.peek().is_none());
+    }
+
+    #[test]
+    fn exhausted_rows_stay_exhausted() {
+        fn assert_fused(mut rows: super::RowIter) {
+            rows.by_ref().for_each(drop);
+            for _ in 0..5 {
+                assert!(rows.next().is_none());
+                assert!(rows.peek().is_none());
+            }
+        }
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Time").unwrap();
+        assert_fused(ws.rows(&mut wb).unwrap());
+        assert_fused(ws.rows_with(&mut wb, &ReadOptions::new().max_rows(2)).unwrap());
+        assert_fused(ws.rows_with(&mut wb, &ReadOptions::new().range("A1:A2")).unwrap());
+        // a dimension that claims more rows than there are must not produce extra empty rows
+        let mut wb = Workbook::open("./tests/data/dimension_overstated.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        assert_fused(ws.rows(&mut wb).unwrap());
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn f64_array() {
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let array = sheets.get("Sheet1").unwrap().to_f64_array(&mut wb).unwrap();
+        assert_eq!(array.ncols(), 18);
+        assert_eq!(array[[0, 0]], 1.0);
+        assert_eq!(array[[5, 5]], 43131.0); // 2018-01-31
+        let array = sheets.get("Time").unwrap().to_f64_array(&mut wb).unwrap();
+        assert_eq!(array[[0, 0]], 0.770_833_333_333_333_4); // 18:30
+        assert!(array[[1, 0]].is_nan());
+    }
+
+    #[test]
+    fn pad_to_dimension() {
+        let mut wb = Workbook::open("./tests/data/dimension_overstated.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let mut rows = ws.rows(&mut wb).unwrap();
+        rows.pad_to_dimension();
+        let rows: Vec<_> = rows.collect();
+        assert_eq!(rows.len(), 10);
+        assert_eq!(rows.iter().map(|r| r.1).collect::<Vec<_>>(), (1..=10).collect::<Vec<_>>());
+        assert!(rows[3..].iter().all(|r| r.0.iter().all(|c| c.value == ExcelValue::None)));
+        // padding still stops at max_rows
+        let mut rows = ws.rows_with(&mut wb, &ReadOptions::new().max_rows(5)).unwrap();
+        rows.pad_to_dimension();
+        assert_eq!(rows.count(), 5);
+    }
+
+    #[test]
+    fn cell_predicates() {
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let rows: Vec<_> = ws.rows(&mut wb).unwrap().take(21).collect();
+        assert!(rows[0][0].is_number() && !rows[0][0].is_date());
+        assert!(rows[5][5].is_date() && !rows[5][5].is_number());
+        assert!(rows[9][7].is_string());
+        assert!(rows[15][8].is_blank());
+        assert!(rows[20][7].is_error());
+        let ws = sheets.get("Time").unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert!(row[0].is_time() && !row[0].is_date());
+        let ws = sheets.get("Sheet1").unwrap();
+        let row1 = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert_eq!(row1[0].as_i64(), Some(1));
+    }
+
+    #[test]
+    fn integers() {
+        assert!(ExcelValue::Number(-4.0).is_integer());
+        assert!(!ExcelValue::Number(0.1).is_integer());
+        assert!(!ExcelValue::Number(f64::INFINITY).is_integer());
+        assert!(!ExcelValue::Number(f64::NAN).is_integer());
+    }
+
+    #[test]
+    fn column() {
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let col = ws.column(&mut wb, 8).unwrap();
+        let rows: Vec<_> = ws.rows(&mut wb).unwrap().collect();
+        assert_eq!(col.len(), rows.len());
+        for (value, row) in col.iter().zip(&rows) {
+            assert_eq!(value, &row[7].value);
+        }
+        assert_eq!(col[9], ExcelValue::String("Test".into()));
+        let ws = sheets.get("Time").unwrap();
+        assert_eq!(ws.column(&mut wb, 1).unwrap()[1], ExcelValue::None);
+    }
+
+    #[test]
+    fn row_spans() {
+        assert_eq!(last_spanned_col("1:5"), Some(5));
+        assert_eq!(last_spanned_col("1:3 5:7"), Some(7));
+        assert_eq!(last_spanned_col("1:16385"), None);
+        assert_eq!(last_spanned_col(""), None);
+        let mut wb = Workbook::open("./tests/data/spans.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let widths: Vec<usize> = ws.rows(&mut wb).unwrap().map(|row| row.0.len()).collect();
+        assert_eq!(widths, vec![4, 4, 6, 6]);
+        let row1 = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert_eq!(row1[3].reference, "D1");
+        assert!(row1[3].is_blank());
+    }
+
+    #[test]
+    fn cell_by_column() {
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let row1 = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert_eq!(row1.cell("A").unwrap().value, ExcelValue::Number(1.0));
+        assert_eq!(row1.cell("R").unwrap().value, ExcelValue::Number(18.0));
+        assert!(row1.cell("S").is_none());
+        assert!(row1.cell("XFE").is_none());
+        assert!(row1.cell("1").is_none());
+        // a blank that fills a gap is found by its column too
+        let mut wb = Workbook::open("./tests/data/sparse.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let row5 = ws.rows(&mut wb).unwrap().nth(4).unwrap();
+        assert!(row5.cell("B").unwrap().is_blank());
+        assert_eq!(row5.cell("D").unwrap().value, ExcelValue::Number(3.0));
+    }
+
+    #[test]
+    fn sparse_cells() {
+        let mut wb = Workbook::open("./tests/data/sparse.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let cells: Vec<_> = ws.cells(&mut wb).unwrap().map(|(at, value)| (at.to_string(), value)).collect();
+        assert_eq!(cells, vec![
+            ("B2".to_owned(), ExcelValue::String("answer".into())),
+            ("A5".to_owned(), ExcelValue::Number(1.0)),
+            ("D5".to_owned(), ExcelValue::Number(3.0)),
+            ("F9".to_owned(), ExcelValue::Bool(true)),
+        ]);
+        // the rows in between are still there for `rows`
+        assert_eq!(ws.rows(&mut wb).unwrap().count(), 9);
+        let mut wb = Workbook::open("./tests/data/out_of_order.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let rows: Vec<u32> = sheets.get("Swapped").unwrap().cells(&mut wb).unwrap().map(|(at, _)| at.row).collect();
+        assert!(rows.windows(2).all(|w| w[0] <= w[1]), "{:?}", rows);
+    }
+
+    #[test]
+    fn region() {
+        use ExcelValue::{None as Blank, Number};
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        // runs past the last row and column of the worksheet
+        let region = ws.region(&mut wb, "Q45:S47").unwrap();
+        assert_eq!(region, vec![
+            vec![Number(809.0), Number(810.0), Blank],
+            vec![Number(827.0), Number(828.0), Blank],
+            vec![Blank, Blank, Blank],
+        ]);
+        assert_eq!(ws.region(&mut wb, "H10").unwrap(), vec![vec![ExcelValue::String("Test".into())]]);
+        assert!(matches!(ws.region(&mut wb, "C3:A1"), Err(XlError::BadOption(_))));
+    }
+
+    #[test]
+    fn trim_strings() {
+        let mut wb = Workbook::open("./tests/data/whitespace.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let values = |wb: &mut Workbook, opts: &ReadOptions| -> Vec<String> {
+            let row = ws.rows_with(wb, opts).unwrap().next().unwrap();
+            row.0.iter().map(|c| match &c.value {
+                ExcelValue::String(s) => s.to_string(),
+                v => v.to_string(),
+            }).collect()
+        };
+        assert_eq!(values(&mut wb, &ReadOptions::new()), ["Acme\u{a0}", "two   words", "Beta\t\tGamma\u{a0}", "5"]);
+        let trimmed = values(&mut wb, &ReadOptions::new().trim_strings(true));
+        assert_eq!(trimmed, ["Acme", "two   words", "Beta\t\tGamma", "5"]);
+        let collapsed = values(&mut wb, &ReadOptions::new().collapse_whitespace(true));
+        assert_eq!(collapsed, ["Acme", "two words", "Beta Gamma", "5"]);
+        let row = ws.rows_with(&mut wb, &ReadOptions::new().trim_strings(true)).unwrap().next().unwrap();
+        assert_eq!(row[2].raw_value, "Beta\t\tGamma\u{a0}");
+        assert_eq!(row[0].raw_text(), "Acme\u{a0}");
+    }
+
+    #[test]
+    fn raw_text() {
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let row10 = ws.rows(&mut wb).unwrap().nth(9).unwrap();
+        let index: usize = row10[7].raw_value.parse().unwrap();
+        assert_eq!(row10[7].shared_text, Some("Test"));
+        assert_eq!(row10[7].raw_text(), "Test");
+        assert_eq!(row10[0].shared_text, None);
+        assert_eq!(row10[0].raw_text(), "163");
+        drop(row10);
+        assert_eq!(wb.shared_string(index), Some("Test"));
+        // cells can be built by hand, too
+        let cell = Cell { raw_value: "0".to_owned(), shared_text: Some("made up"), ..Cell::default() };
+        assert_eq!(cell.raw_text(), "made up");
+    }
+
+    #[test]
+    fn shared_strings_are_borrowed() {
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let row10 = ws.rows(&mut wb).unwrap().nth(9).unwrap();
+        assert!(matches!(row10[7].value, ExcelValue::String(Cow::Borrowed("Test"))));
+        let row10 = ws.rows_with(&mut wb, &ReadOptions::new().trim_strings(true)).unwrap().nth(9).unwrap();
+        assert!(matches!(row10[7].value, ExcelValue::String(Cow::Borrowed("Test"))));
+    }
+
+    #[test]
+    fn explicit_number_type() {
+        let mut wb = Workbook::open("./tests/data/explicit_numbers.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert_eq!(row[0].value, ExcelValue::Number(42.5));
+        assert_eq!(row[1].value, ExcelValue::Date(chrono::NaiveDate::from_ymd_opt(2018, 1, 1).unwrap()));
+        assert_eq!(row[2].value, ExcelValue::Date(chrono::NaiveDate::from_ymd_opt(2018, 1, 2).unwrap()));
+    }
+
+    #[test]
+    fn error_text() {
+        let mut wb = Workbook::open("./tests/data/error_text.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert_eq!(row[0].value, ExcelValue::String("#N/A".into()));
+        assert!(!row[0].is_error());
+        let row = ws.rows_with(&mut wb, &ReadOptions::new().detect_error_text(true)).unwrap().next().unwrap();
+        assert_eq!(row[0].value, ExcelValue::Error(CellError::NA));
+        assert_eq!(row[1].value, ExcelValue::Error(CellError::Div0));
+        assert_eq!(row[2].value, ExcelValue::String("#tag".into()));
+        // text typed into a cell stays text
+        assert_eq!(row[3].value, ExcelValue::String("#N/A".into()));
+        assert_eq!(row[4].value, ExcelValue::Error(CellError::Div0));
+    }
+
+    #[test]
+    fn print_area() {
+        let mut wb = Workbook::open("./tests/data/print_area.xlsx").unwrap();
+        let sheets = wb.sheets();
+        assert_eq!(sheets.get("Summary").unwrap().print_area(&mut wb), None);
+        let ws = sheets.get("My Data").unwrap();
+        assert_eq!(ws.print_area(&mut wb), Some("'My Data'!$A$1:$B$20,'My Data'!$D$1:$E$5".to_owned()));
+        assert_eq!(wb.defined_name("totals", None), Some("Summary!$B$1".to_owned()));
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        assert_eq!(sheets.get(1).unwrap().print_area(&mut wb), None);
+        let ws = Worksheet::new("Sheet1".to_owned(), 0, "xl/worksheets/sheet1.xml".to_owned());
+        assert_eq!(ws.print_area(&mut wb), None);
+    }
+
+    #[test]
+    fn comments() {
+        // comments.xlsx is synthetic: it was written by a script rather than saved by Excel, so it
+        // only has the comment, thread, and person parts (no styles or docProps), and its GUIDs
+        // were made up by hand
+        let mut wb = Workbook::open("./tests/data/comments.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let comments = sheets.get("Sheet1").unwrap().comments(&mut wb);
+        // the note Excel leaves in B2 for older versions is replaced by the thread itself
+        assert_eq!(comments.len(), 2);
+        let note = &comments[0];
+        assert_eq!((note.anchor.to_string(), &note.author[..]), ("A1".to_owned(), "Kevin Ryan"));
+        assert_eq!(note.text, "Kevin Ryan:\nCheck this & that");
+        assert!(!note.threaded && note.replies.is_empty());
+        let thread = &comments[1];
+        assert!(thread.threaded);
+        assert_eq!(thread.text, "Is this right?");
+        let replies: Vec<_> = thread.replies.iter().map(|r| (&r.author[..], &r.text[..])).collect();
+        assert_eq!(replies, [("Bob Jones", "Yes, checked it"), ("Alice Smith", "Thanks")]);
+        assert!(sheets.get("Plain").unwrap().comments(&mut wb).is_empty());
+    }
+
+    #[test]
+    fn second_pass() {
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let opts = ReadOptions::new().range("B2:D10");
+        let first: Vec<String> = ws.rows_with(&mut wb, &opts).unwrap().map(|r| r.to_string()).collect();
+        let second: Vec<String> = ws.rows_with(&mut wb, &opts).unwrap().map(|r| r.to_string()).collect();
+        assert_eq!(first.len(), 9);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn rows_out_of_order() {
+        let mut wb = Workbook::open("./tests/data/out_of_order.xlsx").unwrap();
+        let sheets = wb.sheets();
+        // row 5 comes before rows 3 and 4 in the xml
+        let mut rows = sheets.get("Swapped").unwrap().rows(&mut wb).unwrap();
+        let found: Vec<(usize, String)> = rows.by_ref().map(|r| (r.1, r.to_string())).collect();
+        let expected: Vec<(usize, String)> = (1..=5).map(|n| (n, format!("{},\"row {}\"", n * 10, n))).collect();
+        assert_eq!(found, expected);
+        assert_eq!(rows.dimension_mismatch(), None);
+        drop(rows);
+        // row 2 comes after rows 3 and 4, too late to fill its spot, so it is left out
+        let mut rows = sheets.get("Late").unwrap().rows(&mut wb).unwrap();
+        let found: Vec<(usize, String)> = rows.by_ref().map(|r| (r.1, r.to_string())).collect();
+        assert_eq!(found, [(1, "10,\"row 1\"".to_owned()), (2, ",".to_owned()), (3, "30,\"row 3\"".to_owned()), (4, "40,\"row 4\"".to_owned())]);
+        assert_eq!(rows.late_rows(), [2]);
+    }
+
+    #[test]
+    fn filter_column() {
+        let mut wb = Workbook::open("./tests/data/out_of_order.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Late").unwrap();
+        // the rows are 1, 3, 4, and then 2 (too late to fill its spot, which is left empty, so
+        // the row itself is left out)
+        let mut rows = ws.rows(&mut wb).unwrap();
+        rows.filter_column(1, |c| c.is_blank() || c.as_i64().is_some_and(|n| n >= 30));
+        let found: Vec<(usize, String)> = rows.map(|r| (r.1, r.to_string())).collect();
+        assert_eq!(found, [(2, ",".to_owned()), (3, "30,\"row 3\"".to_owned()), (4, "40,\"row 4\"".to_owned())]);
+        let mut rows = ws.rows(&mut wb).unwrap();
+        rows.filter_column(1, |c| !c.is_blank());
+        assert_eq!(rows.map(|r| r.1).collect::<Vec<_>>(), [1, 3, 4]);
+        // the column tested does not have to be one of the columns returned
+        let mut rows = ws.rows(&mut wb).unwrap();
+        rows.select_columns(&[2]);
+        rows.filter_column(1, |c| c.as_i64().is_some_and(|n| n >= 30));
+        let found: Vec<String> = rows.map(|r| r.to_string()).collect();
+        assert_eq!(found, ["\"row 3\"", "\"row 4\""]);
+    }
+
+    #[test]
+    fn cell_types() {
+        use chrono::{NaiveDate, NaiveTime};
+        let mut wb = Workbook::open("./tests/data/cell_types.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        let types: Vec<_> = row.0.iter().map(|c| c.cell_type.to_string()).collect();
+        // (the last cell has no value, so it comes back as a blank like any other empty cell)
+        assert_eq!(types, ["n", "n", "s", "str", "inlineStr", "b", "e", "d", "d", "d", "d", "d", "zz", "zz", "n"]);
+        assert_eq!(row[12].cell_type, CellType::Other("zz".to_owned()));
+        let values: Vec<_> = row.0.into_iter().map(|c| c.value).collect();
+        let date = NaiveDate::from_ymd_opt(2021, 3, 7).unwrap();
+        assert_eq!(values, vec![
+            ExcelValue::Number(1.5),
+            ExcelValue::Number(2.0),
+            ExcelValue::String(Cow::Borrowed("shared")),
+            ExcelValue::String(Cow::Borrowed("xy")),
+            ExcelValue::String(Cow::Borrowed("inline")),
+            ExcelValue::Bool(true),
+            ExcelValue::Error(CellError::Ref),
+            ExcelValue::DateTime(date.and_hms_opt(15, 4, 5).unwrap()),
+            ExcelValue::Date(date),
+            ExcelValue::Date(date),
+            ExcelValue::Time(NaiveTime::from_hms_milli_opt(15, 4, 5, 250).unwrap()),
+            ExcelValue::String(Cow::Borrowed("soon")),
+            // types that are not in the spec are read as numbers, or as text if they are not one
+            ExcelValue::Number(7.0),
+            ExcelValue::String(Cow::Borrowed("abc")),
+            ExcelValue::None,
+        ]);
+    }
 }